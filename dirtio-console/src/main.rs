@@ -0,0 +1,54 @@
+//! Terminal UI inspecting a running dirtio runtime, which serves the
+//! console with `Builder::enable_console`.
+//!
+//! Usage: `dirtio-console <socket path>`
+
+mod render;
+mod snapshot;
+
+#[cfg(unix)]
+fn main() {
+    use snapshot::Snapshot;
+
+    use std::io::{self, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process;
+
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: dirtio-console <socket path>");
+        process::exit(2);
+    };
+
+    let stream = UnixStream::connect(&path).unwrap_or_else(|e| {
+        eprintln!("failed to connect to {}: {}", path, e);
+        process::exit(1);
+    });
+    let mut reader = BufReader::new(stream);
+
+    let mut prev = None;
+    loop {
+        match Snapshot::read(&mut reader) {
+            Ok(Some(snapshot)) => {
+                let screen = render::render(&path, &snapshot, prev.as_ref());
+                let mut stdout = io::stdout().lock();
+                let _ = stdout.write_all(screen.as_bytes());
+                let _ = stdout.flush();
+                prev = Some(snapshot);
+            }
+            Ok(None) => {
+                println!("the runtime went away");
+                return;
+            }
+            Err(e) => {
+                eprintln!("failed to read from {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("dirtio-console only supports Unix");
+    std::process::exit(1);
+}
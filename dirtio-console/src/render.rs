@@ -0,0 +1,138 @@
+use crate::snapshot::Snapshot;
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Max number of tasks and resources shown.
+const MAX_ROWS: usize = 20;
+
+const CLEAR: &str = "\x1b[H\x1b[2J";
+const BOLD: &str = "\x1b[1m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Draw the snapshot, with the rates since the previous one.
+pub fn render(path: &str, snapshot: &Snapshot, prev: Option<&Snapshot>) -> String {
+    let mut out = String::from(CLEAR);
+    let _ = writeln!(
+        out,
+        "{BOLD}dirtio-console{RESET}  {}  uptime {}",
+        path,
+        duration(snapshot.uptime)
+    );
+
+    let elapsed = prev.map_or(Duration::ZERO, |prev| {
+        snapshot.uptime.saturating_sub(prev.uptime)
+    });
+
+    // Tasks spawned and completed since the previous snapshot.
+    let live: HashSet<u64> = snapshot.tasks.iter().map(|task| task.id).collect();
+    let _ = write!(out, "{} live tasks", live.len());
+    if let Some(prev) = prev {
+        let prev_live: HashSet<u64> = prev.tasks.iter().map(|task| task.id).collect();
+        let _ = write!(
+            out,
+            ", +{} spawned, -{} completed",
+            live.difference(&prev_live).count(),
+            prev_live.difference(&live).count(),
+        );
+    }
+    out.push_str("\n\n");
+
+    let _ = writeln!(
+        out,
+        "{BOLD}{:>6} {:>10} {:>12} {:>10} {:>10} {:>10}{RESET}",
+        "WORKER", "POLLS/S", "POLLS", "STEALS", "PARKS", "BUSY"
+    );
+    for worker in &snapshot.workers {
+        let prev_polls = prev
+            .and_then(|prev| prev.workers.get(worker.index))
+            .map_or(worker.polls, |prev| prev.polls);
+        let _ = writeln!(
+            out,
+            "{:>6} {:>10} {:>12} {:>10} {:>10} {:>10}",
+            worker.index,
+            rate(worker.polls - prev_polls.min(worker.polls), elapsed),
+            worker.polls,
+            worker.steals,
+            worker.parks,
+            duration(worker.busy),
+        );
+    }
+    out.push('\n');
+
+    let mut tasks: Vec<_> = snapshot.tasks.iter().collect();
+    tasks.sort_by_key(|task| std::cmp::Reverse(task.busy));
+    let _ = writeln!(
+        out,
+        "{BOLD}{:>8} {:<10} {:>10} {:>10} {:>10}  {:<20} LOCATION{RESET}",
+        "ID", "STATE", "POLLS", "WAKES", "BUSY", "NAME"
+    );
+    for task in tasks.iter().take(MAX_ROWS) {
+        let color = match task.state.as_str() {
+            "running" => GREEN,
+            "scheduled" => YELLOW,
+            _ => "",
+        };
+        let _ = writeln!(
+            out,
+            "{:>8} {color}{:<10}{RESET} {:>10} {:>10} {:>10}  {:<20} {}",
+            task.id,
+            task.state,
+            task.polls,
+            task.wakes,
+            duration(task.busy),
+            task.name,
+            task.location,
+        );
+    }
+    more(&mut out, tasks.len());
+    out.push('\n');
+
+    let _ = writeln!(
+        out,
+        "{BOLD}{:>8} {:<14} {:>6} {:>10} {:>10} {:>12}{RESET}",
+        "FD", "KIND", "DRIVER", "READ", "WRITE", "WOULDBLOCK"
+    );
+    for resource in snapshot.resources.iter().take(MAX_ROWS) {
+        let _ = writeln!(
+            out,
+            "{:>8} {:<14} {:>6} {:>10} {:>10} {:>12}",
+            resource.fd,
+            resource.kind,
+            resource.driver,
+            resource.read_ready,
+            resource.write_ready,
+            resource.would_block,
+        );
+    }
+    more(&mut out, snapshot.resources.len());
+
+    out
+}
+
+fn more(out: &mut String, len: usize) {
+    if len > MAX_ROWS {
+        let _ = writeln!(out, "         ... {} more", len - MAX_ROWS);
+    }
+}
+
+fn rate(count: u64, elapsed: Duration) -> String {
+    if elapsed.is_zero() {
+        return "-".to_string();
+    }
+    format!("{:.0}", count as f64 / elapsed.as_secs_f64())
+}
+
+fn duration(d: Duration) -> String {
+    let secs = d.as_secs_f64();
+    if secs >= 1.0 {
+        format!("{:.2}s", secs)
+    } else if secs >= 1e-3 {
+        format!("{:.2}ms", secs * 1e3)
+    } else {
+        format!("{:.0}us", secs * 1e6)
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, BufRead};
+use std::time::Duration;
+
+/// A snapshot of the runtime, see the console module of `dirtio` for the
+/// format.
+#[derive(Default)]
+pub struct Snapshot {
+    pub uptime: Duration,
+    pub workers: Vec<Worker>,
+    pub tasks: Vec<Task>,
+    pub resources: Vec<Resource>,
+}
+
+pub struct Worker {
+    pub index: usize,
+    pub polls: u64,
+    pub steals: u64,
+    pub parks: u64,
+    pub busy: Duration,
+}
+
+pub struct Task {
+    pub id: u64,
+    pub state: String,
+    pub polls: u64,
+    pub wakes: u64,
+    pub busy: Duration,
+    pub location: String,
+    pub name: String,
+}
+
+pub struct Resource {
+    pub fd: i64,
+    pub kind: String,
+    pub driver: usize,
+    pub read_ready: u64,
+    pub write_ready: u64,
+    pub would_block: u64,
+}
+
+impl Snapshot {
+    /// Read the next snapshot, `None` if the runtime went away.
+    pub fn read(reader: &mut impl BufRead) -> io::Result<Option<Snapshot>> {
+        let mut snapshot = Snapshot::default();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let fields: Vec<&str> = line.trim_end_matches('\n').split('\t').collect();
+            match fields[..] {
+                ["snapshot", uptime] => {
+                    snapshot.uptime = Duration::from_millis(num(uptime)?);
+                }
+                ["worker", index, polls, steals, parks, busy] => snapshot.workers.push(Worker {
+                    index: num(index)?,
+                    polls: num(polls)?,
+                    steals: num(steals)?,
+                    parks: num(parks)?,
+                    busy: Duration::from_nanos(num(busy)?),
+                }),
+                ["task", id, state, polls, wakes, busy, location, name] => {
+                    snapshot.tasks.push(Task {
+                        id: num(id)?,
+                        state: state.to_string(),
+                        polls: num(polls)?,
+                        wakes: num(wakes)?,
+                        busy: Duration::from_nanos(num(busy)?),
+                        location: location.to_string(),
+                        name: name.to_string(),
+                    })
+                }
+                ["resource", fd, kind, driver, read_ready, write_ready, would_block] => {
+                    snapshot.resources.push(Resource {
+                        fd: num(fd)?,
+                        kind: kind.to_string(),
+                        driver: num(driver)?,
+                        read_ready: num(read_ready)?,
+                        write_ready: num(write_ready)?,
+                        would_block: num(would_block)?,
+                    })
+                }
+                ["end"] => return Ok(Some(snapshot)),
+                _ => return Err(invalid(&line)),
+            }
+        }
+    }
+}
+
+fn num<T: std::str::FromStr>(s: &str) -> io::Result<T> {
+    s.parse().map_err(|_| invalid(s))
+}
+
+fn invalid(s: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid record: {:?}", s.trim_end()),
+    )
+}
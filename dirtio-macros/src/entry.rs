@@ -0,0 +1,280 @@
+//! Expansion of the attributes setting up a runtime to run an async fn.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Expr, ItemFn, Lit, MetaNameValue, Path, ReturnType, Token};
+
+/// The attribute being expanded.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Main,
+    Test,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+/// Settings of the runtime, from the arguments of the attribute.
+struct Config {
+    /// Path of the `dirtio` crate.
+    krate: Path,
+    flavor: Flavor,
+    worker_threads: Option<(usize, Span)>,
+    start_paused: Option<(bool, Span)>,
+}
+
+impl Config {
+    fn parse(args: TokenStream, default: Flavor) -> syn::Result<Self> {
+        let args = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args)?;
+
+        let mut krate = None;
+        let mut flavor = None;
+        let mut worker_threads = None;
+        let mut start_paused = None;
+        for arg in &args {
+            let Some(name) = arg.path.get_ident() else {
+                return Err(syn::Error::new_spanned(&arg.path, "unknown argument"));
+            };
+            match name.to_string().as_str() {
+                "crate" => {
+                    check_unset(&krate, name)?;
+                    let value = parse_str(&arg.value, "crate")?;
+                    krate = Some(syn::parse_str(&value).map_err(|_| {
+                        syn::Error::new_spanned(&arg.value, "`crate` expects a path")
+                    })?);
+                }
+                "flavor" => {
+                    check_unset(&flavor, name)?;
+                    let value = parse_str(&arg.value, "flavor")?;
+                    flavor = Some(match value.as_str() {
+                        "current_thread" => Flavor::CurrentThread,
+                        "multi_thread" => Flavor::MultiThread,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                &arg.value,
+                                "unknown flavor, expected `current_thread` or `multi_thread`",
+                            ))
+                        }
+                    });
+                }
+                "worker_threads" => {
+                    check_unset(&worker_threads, name)?;
+                    let value = parse_int(&arg.value, "worker_threads")?;
+                    if value == 0 {
+                        return Err(syn::Error::new_spanned(
+                            &arg.value,
+                            "`worker_threads` may not be 0",
+                        ));
+                    }
+                    worker_threads = Some((value, arg.span()));
+                }
+                "start_paused" => {
+                    check_unset(&start_paused, name)?;
+                    let value = parse_bool(&arg.value, "start_paused")?;
+                    start_paused = Some((value, arg.span()));
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        name,
+                        "unknown argument, expected `crate`, `flavor`, `worker_threads` or \
+                         `start_paused`",
+                    ))
+                }
+            }
+        }
+
+        let flavor = flavor.unwrap_or(default);
+        match (flavor, worker_threads, start_paused) {
+            (Flavor::CurrentThread, Some((_, span)), _) => Err(syn::Error::new(
+                span,
+                "`worker_threads` is only supported by the `multi_thread` flavor",
+            )),
+            (Flavor::MultiThread, _, Some((true, span))) => Err(syn::Error::new(
+                span,
+                "`start_paused` is only supported by the `current_thread` flavor",
+            )),
+            _ => Ok(Self {
+                krate: krate.unwrap_or_else(|| syn::parse_quote!(::dirtio)),
+                flavor,
+                worker_threads,
+                start_paused,
+            }),
+        }
+    }
+
+    /// Expression building the runtime.
+    fn build(&self) -> TokenStream {
+        let krate = &self.krate;
+        let mut builder = match self.flavor {
+            Flavor::CurrentThread => quote! { #krate::runtime::Builder::new_current_thread() },
+            Flavor::MultiThread => quote! { #krate::runtime::Builder::new_multi_thread() },
+        };
+        if let Some((n, span)) = self.worker_threads {
+            builder = quote_spanned! {span=> #builder.worker_threads(#n) };
+        }
+        // Only set if `true`, `start_paused` requires the `test-util`
+        // feature.
+        if let Some((true, span)) = self.start_paused {
+            builder = quote_spanned! {span=> #builder.start_paused(true) };
+        }
+        quote! {
+            #builder
+                .build()
+                .expect("failed to build runtime")
+        }
+    }
+}
+
+fn check_unset<T>(value: &Option<T>, name: &syn::Ident) -> syn::Result<()> {
+    match value {
+        Some(_) => Err(syn::Error::new_spanned(
+            name,
+            format!("`{}` set more than once", name),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn lit(value: &Expr) -> Option<&Lit> {
+    match value {
+        Expr::Lit(lit) => Some(&lit.lit),
+        _ => None,
+    }
+}
+
+fn parse_str(value: &Expr, name: &str) -> syn::Result<String> {
+    match lit(value) {
+        Some(Lit::Str(s)) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` expects a string", name),
+        )),
+    }
+}
+
+fn parse_int(value: &Expr, name: &str) -> syn::Result<usize> {
+    match lit(value) {
+        Some(Lit::Int(i)) => i.base10_parse(),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` expects an integer", name),
+        )),
+    }
+}
+
+fn parse_bool(value: &Expr, name: &str) -> syn::Result<bool> {
+    match lit(value) {
+        Some(Lit::Bool(b)) => Ok(b.value),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` expects a bool", name),
+        )),
+    }
+}
+
+/// Expand `#[dirtio::main]`, the function runs on a multi thread runtime by
+/// default.
+pub(crate) fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry(args, item, Kind::Main)
+}
+
+/// Expand `#[dirtio::test]`, the test runs on a fresh current thread
+/// runtime by default.
+pub(crate) fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry(args, item, Kind::Test)
+}
+
+fn entry(args: TokenStream, item: TokenStream, kind: Kind) -> TokenStream {
+    let input: ItemFn = match syn::parse2(item.clone()) {
+        Ok(input) => input,
+        Err(e) => {
+            let error = e.into_compile_error();
+            return quote! { #error #item };
+        }
+    };
+    match expand(args, &input, kind) {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            // Keep a function with the same name, so the error isn't
+            // followed by others about it missing.
+            let error = e.into_compile_error();
+            let ItemFn {
+                attrs, vis, sig, ..
+            } = &input;
+            let mut sig = sig.clone();
+            sig.asyncness = None;
+            // The attributes of a test only apply to a `#[test]`.
+            let attrs = match kind {
+                Kind::Main => &attrs[..],
+                Kind::Test => &[],
+            };
+            quote! {
+                #error
+                #(#attrs)*
+                #vis #sig {
+                    ::core::unreachable!()
+                }
+            }
+        }
+    }
+}
+
+fn expand(args: TokenStream, input: &ItemFn, kind: Kind) -> syn::Result<TokenStream> {
+    let config = match kind {
+        Kind::Main => Config::parse(args, Flavor::MultiThread)?,
+        Kind::Test => Config::parse(args, Flavor::CurrentThread)?,
+    };
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "the `async` keyword is missing from the function declaration",
+        ));
+    }
+    if kind == Kind::Test {
+        if !input.sig.inputs.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.sig.inputs,
+                "the test function can't take arguments",
+            ));
+        }
+        if let Some(attr) = input.attrs.iter().find(|attr| attr.path().is_ident("test")) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`#[test]` is applied by `#[dirtio::test]`, remove it",
+            ));
+        }
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = input;
+    let mut sig = sig.clone();
+    sig.asyncness = None;
+    let output = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+    let test = (kind == Kind::Test).then(|| quote! { #[::core::prelude::v1::test] });
+    let build = config.build();
+    // The declared output is spelled out so that `?` in the body infers
+    // its error type.
+    Ok(quote! {
+        #test
+        #(#attrs)*
+        #vis #sig {
+            let body: ::core::pin::Pin<&mut dyn ::core::future::Future<Output = #output>> =
+                ::core::pin::pin!(async move #block);
+            #build.block_on(body)
+        }
+    })
+}
@@ -0,0 +1,109 @@
+//! Expansion of `join!` and `try_join!`.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{Expr, Token};
+
+/// The path of the `dirtio` crate passed by the declarative macro,
+/// followed by the futures.
+struct Input {
+    krate: TokenStream,
+    futures: Vec<Expr>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let krate = crate_path(input)?;
+        let futures = Punctuated::<Expr, Token![,]>::parse_terminated(input)?;
+        Ok(Self {
+            krate,
+            futures: futures.into_iter().collect(),
+        })
+    }
+}
+
+/// Parse the tokens up to the first `;`, the path of the `dirtio` crate.
+pub(crate) fn crate_path(input: ParseStream<'_>) -> syn::Result<TokenStream> {
+    let mut krate = TokenStream::new();
+    while !input.peek(Token![;]) {
+        krate.extend([input.parse::<proc_macro2::TokenTree>()?]);
+    }
+    input.parse::<Token![;]>()?;
+    Ok(krate)
+}
+
+pub(crate) fn join(input: TokenStream) -> syn::Result<TokenStream> {
+    expand(Input::parse.parse2(input)?, false)
+}
+
+pub(crate) fn try_join(input: TokenStream) -> syn::Result<TokenStream> {
+    expand(Input::parse.parse2(input)?, true)
+}
+
+fn expand(input: Input, try_join: bool) -> syn::Result<TokenStream> {
+    let support = {
+        let krate = &input.krate;
+        quote! { #krate::macros::support }
+    };
+    let futures = &input.futures;
+    if futures.is_empty() {
+        return Ok(if try_join {
+            quote! { ::core::result::Result::Ok(()) }
+        } else {
+            quote! { () }
+        });
+    }
+    let names: Vec<_> = (0..futures.len())
+        .map(|i| format_ident!("__future{}", i, span = Span::mixed_site()))
+        .collect();
+
+    // A failed future ends `try_join!` right away.
+    let check = try_join.then(|| {
+        quote! {
+            #(
+                if let ::core::option::Option::Some(::core::result::Result::Err(_)) =
+                    #names.as_mut().output_mut()
+                {
+                    let ::core::option::Option::Some(::core::result::Result::Err(e)) =
+                        #names.as_mut().take_output()
+                    else {
+                        ::core::unreachable!()
+                    };
+                    return #support::Poll::Ready(::core::result::Result::Err(e));
+                }
+            )*
+        }
+    });
+    let output = if try_join {
+        quote! {
+            ::core::result::Result::Ok((#(
+                match #names.as_mut().take_output() {
+                    ::core::option::Option::Some(::core::result::Result::Ok(v)) => v,
+                    _ => ::core::unreachable!(),
+                },
+            )*))
+        }
+    } else {
+        quote! { (#(#names.as_mut().take_output().unwrap(),)*) }
+    };
+
+    Ok(quote! {{
+        #(
+            let mut #names = ::core::pin::pin!(#support::maybe_done(#futures));
+        )*
+        #support::poll_fn(|cx| {
+            let mut done = true;
+            #(
+                done &= #support::Future::poll(#names.as_mut(), cx).is_ready();
+            )*
+            #check
+            if !done {
+                return #support::Poll::Pending;
+            }
+            #support::Poll::Ready(#output)
+        })
+        .await
+    }})
+}
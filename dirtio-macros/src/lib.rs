@@ -1,26 +1,63 @@
+mod entry;
+mod join;
+mod select;
+
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, ItemFn};
 
-/// A simple procedure macro, providing a shorthand
-/// to set up the runtime. The default setting is used.
+/// Run an async fn on a runtime, the entry point of the program.
+///
+/// The runtime is a multi thread runtime unless set otherwise:
+///
+/// - `flavor = "current_thread"` or `flavor = "multi_thread"`.
+/// - `worker_threads = N`, for the multi thread flavor.
+/// - `start_paused = true`, start with the time paused, for the current
+///   thread flavor. Requires the `test-util` feature of `dirtio`.
+/// - `crate = "path"`, the path of `dirtio` if renamed or re-exported.
 #[proc_macro_attribute]
-pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
-    let input: ItemFn = parse_macro_input!(item);
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry::main(args.into(), item.into()).into()
+}
 
-    let fn_name = &input.sig.ident;
-    let fn_output = &input.sig.output;
-    let fn_args = &input.sig.inputs;
-    let fn_body = &input.block;
+/// Run an async test on a fresh runtime, and mark it as a `#[test]`.
+///
+/// The runtime is a current thread runtime unless set otherwise:
+///
+/// - `flavor = "current_thread"` or `flavor = "multi_thread"`.
+/// - `worker_threads = N`, for the multi thread flavor.
+/// - `start_paused = true`, start with the time paused, for the current
+///   thread flavor. Requires the `test-util` feature of `dirtio`.
+/// - `crate = "path"`, the path of `dirtio` if renamed or re-exported.
+///
+/// The test may return a `Result`, like a plain `#[test]`.
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry::test(args.into(), item.into()).into()
+}
 
-    let gen = quote! {
-        fn #fn_name(#fn_args) #fn_output {
-            let rt = dirtio::runtime::Builder::new()
-                .build()
-                .expect("failed to build runtime");
-            rt.block_on(async move { #fn_body })
-        }
-    };
+/// Implementation of `dirtio::select!`, which passes the path of the crate
+/// first.
+#[doc(hidden)]
+#[proc_macro]
+pub fn select(input: TokenStream) -> TokenStream {
+    select::select(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implementation of `dirtio::join!`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn join(input: TokenStream) -> TokenStream {
+    join::join(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    gen.into()
+/// Implementation of `dirtio::try_join!`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn try_join(input: TokenStream) -> TokenStream {
+    join::try_join(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
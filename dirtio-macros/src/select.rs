@@ -0,0 +1,241 @@
+//! Expansion of `select!`.
+
+use crate::join::crate_path;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::visit_mut::{self, VisitMut};
+use syn::{Expr, Pat, PatIdent, Token};
+
+/// Max number of branches, the disabled ones are kept in a `u64`.
+const MAX_BRANCHES: usize = 64;
+
+struct Input {
+    krate: TokenStream,
+    biased: bool,
+    branches: Vec<Branch>,
+    /// Evaluated when all branches are disabled.
+    otherwise: Option<Expr>,
+}
+
+/// `<pattern> = <future>, if <precondition> => <handler>`
+struct Branch {
+    pat: Pat,
+    future: Expr,
+    precondition: Option<Expr>,
+    handler: Expr,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let krate = crate_path(input)?;
+
+        let biased = input.peek(syn::Ident) && input.peek2(Token![;]) && {
+            let fork = input.fork();
+            fork.parse::<syn::Ident>()? == "biased"
+        };
+        if biased {
+            input.parse::<syn::Ident>()?;
+            input.parse::<Token![;]>()?;
+        }
+
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        while !input.is_empty() {
+            if input.peek(Token![else]) {
+                let token = input.parse::<Token![else]>()?;
+                if otherwise.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        token,
+                        "more than one `else` branch",
+                    ));
+                }
+                input.parse::<Token![=>]>()?;
+                otherwise = Some(parse_handler(input)?);
+                continue;
+            }
+
+            let pat = Pat::parse_multi_with_leading_vert(input)?;
+            input.parse::<Token![=]>()?;
+            let future = input.parse()?;
+            let precondition = if input.peek(Token![,]) && input.peek2(Token![if]) {
+                input.parse::<Token![,]>()?;
+                input.parse::<Token![if]>()?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
+            input.parse::<Token![=>]>()?;
+            let handler = parse_handler(input)?;
+            branches.push(Branch {
+                pat,
+                future,
+                precondition,
+                handler,
+            });
+        }
+
+        Ok(Self {
+            krate,
+            biased,
+            branches,
+            otherwise,
+        })
+    }
+}
+
+/// Parse the handler of a branch and the comma ending it, which may be
+/// left out after a block and after the last branch.
+fn parse_handler(input: ParseStream<'_>) -> syn::Result<Expr> {
+    let is_block = input.peek(syn::token::Brace);
+    let handler = input.parse()?;
+    if input.is_empty() || (is_block && !input.peek(Token![,])) {
+        return Ok(handler);
+    }
+    input.parse::<Token![,]>()?;
+    Ok(handler)
+}
+
+/// Strip `ref` and `mut` from the bindings, to check if the pattern
+/// matches a reference to the output without moving it.
+struct CleanPattern;
+
+impl VisitMut for CleanPattern {
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        pat.by_ref = None;
+        pat.mutability = None;
+        visit_mut::visit_pat_ident_mut(self, pat);
+    }
+}
+
+pub(crate) fn select(input: TokenStream) -> syn::Result<TokenStream> {
+    let input = Input::parse.parse2(input)?;
+    let krate = &input.krate;
+    let support = quote! { #krate::macros::support };
+
+    let otherwise = match &input.otherwise {
+        Some(expr) => quote! { #expr },
+        None => quote! {
+            ::core::panic!("all branches are disabled and there is no else branch")
+        },
+    };
+    let n = input.branches.len();
+    if n == 0 {
+        return match input.otherwise {
+            Some(_) => Ok(otherwise),
+            None => Err(syn::Error::new(
+                Span::call_site(),
+                "`select!` needs at least one branch",
+            )),
+        };
+    }
+    if n > MAX_BRANCHES {
+        return Err(syn::Error::new_spanned(
+            &input.branches[MAX_BRANCHES].pat,
+            format!("`select!` supports at most {} branches", MAX_BRANCHES),
+        ));
+    }
+
+    let mixed = |name: &str| format_ident!("{}", name, span = Span::mixed_site());
+    let out = mixed("__Out");
+    let disabled = mixed("__disabled");
+    let output = mixed("__output");
+    let variants: Vec<_> = (0..n)
+        .map(|i| format_ident!("_{}", i, span = Span::mixed_site()))
+        .collect();
+    let params: Vec<_> = (0..n)
+        .map(|i| format_ident!("T{}", i, span = Span::mixed_site()))
+        .collect();
+    let futures: Vec<_> = (0..n)
+        .map(|i| format_ident!("__future{}", i, span = Span::mixed_site()))
+        .collect();
+    let indexes: Vec<_> = (0..n).collect();
+    let all = u64::MAX >> (MAX_BRANCHES - n);
+
+    let preconditions = input.branches.iter().enumerate().map(|(i, branch)| {
+        branch.precondition.as_ref().map(|precondition| {
+            quote! {
+                if !(#precondition) {
+                    #disabled |= 1 << #i;
+                }
+            }
+        })
+    });
+    let exprs = input.branches.iter().map(|branch| &branch.future);
+    let clean_pats = input.branches.iter().map(|branch| {
+        let mut pat = branch.pat.clone();
+        CleanPattern.visit_pat_mut(&mut pat);
+        pat
+    });
+    let pats = input.branches.iter().map(|branch| &branch.pat);
+    let handlers = input.branches.iter().map(|branch| &branch.handler);
+    let start = if input.biased {
+        quote! { 0 }
+    } else {
+        quote! { #support::thread_rng_n(#n) }
+    };
+
+    // The futures are dropped before the handler runs, so it may use what
+    // they borrowed, and `break`, `continue`, `return` and `.await` in it
+    // apply to the enclosing code.
+    Ok(quote! {{
+        enum #out<#(#params,)*> {
+            #(#variants(#params),)*
+            Disabled,
+        }
+
+        let #output = {
+            let mut #disabled: u64 = 0;
+            #(#preconditions)*
+            #(
+                let mut #futures =
+                    ::core::pin::pin!(#support::IntoFuture::into_future(#exprs));
+            )*
+            #support::poll_fn(|cx| {
+                let start = #start;
+                for i in 0..#n {
+                    let mut branch = start + i;
+                    if branch >= #n {
+                        branch -= #n;
+                    }
+                    if #disabled & (1 << branch) != 0 {
+                        continue;
+                    }
+                    match branch {
+                        #(
+                            #indexes => {
+                                let out = match #support::Future::poll(#futures.as_mut(), cx) {
+                                    #support::Poll::Ready(out) => out,
+                                    #support::Poll::Pending => continue,
+                                };
+                                #disabled |= 1 << #indexes;
+                                // The branch is skipped if its pattern doesn't match.
+                                #[allow(unused_variables, unreachable_patterns)]
+                                match &out {
+                                    #clean_pats => {}
+                                    _ => continue,
+                                }
+                                return #support::Poll::Ready(#out::#variants(out));
+                            }
+                        )*
+                        _ => ::core::unreachable!(),
+                    }
+                }
+                if #disabled == #all {
+                    #support::Poll::Ready(#out::Disabled)
+                } else {
+                    #support::Poll::Pending
+                }
+            })
+            .await
+        };
+
+        match #output {
+            #(#out::#variants(#pats) => #handlers,)*
+            #out::Disabled => #otherwise,
+            #[allow(unreachable_patterns)]
+            _ => ::core::unreachable!("the pattern of the branch was checked"),
+        }
+    }})
+}
@@ -2,7 +2,7 @@ use dirtio::net::tcp::TcpListener;
 use futures::{AsyncReadExt, AsyncWriteExt};
 
 #[dirtio::main]
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr = "127.0.0.1:8080";
 
     let mut listener = TcpListener::bind(addr.parse().unwrap())?;
@@ -20,7 +20,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("failed to read data: {}", e);
                         break;
                     }
-                    Ok(n) if n == 0 => break,
+                    Ok(0) => break,
                     Ok(n) => {
                         if let Err(e) = stream.write_all(&buf[0..n]).await {
                             println!("failed to write data: {}", e);
@@ -1,7 +1,7 @@
 use dirtio::net::udp::UdpSocket;
 
 #[dirtio::main]
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr = "127.0.0.1:8080";
     let mut buf = [0; 65535];
     let socket = UdpSocket::bind(addr.parse().unwrap())?;
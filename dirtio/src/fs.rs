@@ -0,0 +1,65 @@
+use crate::io::uring::Op;
+use crate::runtime::context;
+
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// A file with owned buffer operations.
+///
+/// The operations are completion based when the kernel supports
+/// `io_uring`, otherwise they block the calling worker.
+pub struct File {
+    std: std::fs::File,
+}
+
+impl File {
+    /// Opens a file in read-only mode.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_std(std::fs::File::open(path)?))
+    }
+
+    /// Opens a file in write-only mode, creates or truncates it.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_std(std::fs::File::create(path)?))
+    }
+
+    pub fn from_std(std: std::fs::File) -> Self {
+        Self { std }
+    }
+
+    /// Reads into an owned buffer at the offset, returns the number of
+    /// bytes read along with the buffer.
+    pub async fn read_at(&self, mut buf: Vec<u8>, pos: u64) -> (io::Result<usize>, Vec<u8>) {
+        let handle = context::current();
+        if let Some(uring) = handle.drivers[handle.pick_driver()].uring() {
+            return Op::read_at(uring.clone(), self.std.as_raw_fd(), buf, pos).await;
+        }
+
+        let res = self.std.read_at(&mut buf, pos);
+        (res, buf)
+    }
+
+    /// Writes an owned buffer at the offset, returns the number of bytes
+    /// written along with the buffer.
+    pub async fn write_at(&self, buf: Vec<u8>, pos: u64) -> (io::Result<usize>, Vec<u8>) {
+        let handle = context::current();
+        if let Some(uring) = handle.drivers[handle.pick_driver()].uring() {
+            return Op::write_at(uring.clone(), self.std.as_raw_fd(), buf, pos).await;
+        }
+
+        let res = self.std.write_at(&buf, pos);
+        (res, buf)
+    }
+
+    /// Flushes the data and metadata to disk.
+    pub async fn sync_all(&self) -> io::Result<()> {
+        let handle = context::current();
+        if let Some(uring) = handle.drivers[handle.pick_driver()].uring() {
+            return Op::fsync(uring.clone(), self.std.as_raw_fd()).await;
+        }
+
+        self.std.sync_all()
+    }
+}
@@ -1,22 +1,67 @@
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use super::uring::Uring;
+use super::IoSource;
+
+use std::collections::HashSet;
 use std::io;
-use std::sync::Arc;
+#[cfg(feature = "tracing")]
+use std::num::NonZeroU64;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
-use mio::event::{Event, Source};
-use mio::{Events, Interest, Poll, Registry, Token};
+use mio::event::Event;
+use mio::{Events, Interest, Poll, Registry, Token, Waker};
 use sharded_slab::Slab;
 
+/// Token of the waker, the slab never hands it out.
+const WAKE_TOKEN: Token = Token(usize::MAX - 1);
+
+/// Token of the `io_uring` instance, the slab never hands it out.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+const URING_TOKEN: Token = Token(usize::MAX);
+
 pub(crate) struct Driver {
     poll: Poll,
     events: Events,
-    wakers: Arc<Slab<UnboundedSender<Event>>>,
+    wakers: Arc<Slab<ScheduledIo>>,
+    metrics: Arc<DriverMetrics>,
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    uring: Option<Arc<Uring>>,
+}
+
+/// The events of a source are sent to its registration.
+struct ScheduledIo {
+    sender: UnboundedSender<Event>,
+    #[cfg(feature = "tracing")]
+    fd: i64,
+    /// Id of the task last waiting for an event, `0` if none.
+    #[cfg(feature = "tracing")]
+    task: AtomicU64,
+}
+
+/// Counters of a driver.
+#[derive(Default)]
+pub(crate) struct DriverMetrics {
+    pub(crate) ready_count: AtomicU64,
+    source_count: AtomicUsize,
 }
 
 /// Handle to the IO driver.
 pub(crate) struct Handle {
     registry: Registry,
-    wakers: Arc<Slab<UnboundedSender<Event>>>,
+    wakers: Arc<Slab<ScheduledIo>>,
+    /// Tokens of the registered sources, `None` once shut down.
+    tokens: Mutex<Option<HashSet<usize>>>,
+    waker: Waker,
+    metrics: Arc<DriverMetrics>,
+    #[cfg(unix)]
+    fd: RawFd,
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    uring: Option<Arc<Uring>>,
 }
 
 impl Driver {
@@ -24,53 +69,209 @@ impl Driver {
         let poll = Poll::new()?;
         let registry = poll.registry().try_clone()?;
         let wakers = Arc::new(Slab::new());
+        let metrics = Arc::new(DriverMetrics::default());
+        let waker = Waker::new(&registry, WAKE_TOKEN)?;
+        #[cfg(unix)]
+        let fd = poll.as_raw_fd();
+
+        // Fall back to readiness based IO if the kernel lacks support.
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        let uring = match Uring::new() {
+            Some(uring) => {
+                // Completions make the ring readable, so the driver
+                // gets woken up by them.
+                let fd = uring.as_raw_fd();
+                registry.register(
+                    &mut mio::unix::SourceFd(&fd),
+                    URING_TOKEN,
+                    Interest::READABLE,
+                )?;
+                Some(Arc::new(uring))
+            }
+            None => None,
+        };
 
         let driver = Driver {
             poll,
             events: Events::with_capacity(128),
             wakers: wakers.clone(),
+            metrics: metrics.clone(),
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            uring: uring.clone(),
+        };
+        let handle = Handle {
+            registry,
+            wakers,
+            tokens: Mutex::new(Some(HashSet::new())),
+            waker,
+            metrics,
+            #[cfg(unix)]
+            fd,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            uring,
         };
-        let handle = Handle { registry, wakers };
 
         Ok((driver, handle))
     }
 
-    /// Poll events and dispatch.
-    pub(crate) fn poll_events(&mut self) {
+    /// Poll events and dispatch, blocks at most `timeout`.
+    pub(crate) fn poll_events(&mut self, timeout: Option<Duration>) {
+        // Submit the operations queued since last turn in one batch.
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if let Some(uring) = &self.uring {
+            uring.submit();
+        }
+
         self.poll
-            .poll(&mut self.events, Some(Duration::from_micros(100)))
+            .poll(&mut self.events, timeout)
             .expect("failed to poll events");
 
+        let mut ready = 0;
         for event in self.events.iter() {
-            if let Some(sender) = self.wakers.get(event.token().0) {
-                let _ = sender.unbounded_send(event.clone());
+            if let Some(io) = self.wakers.get(event.token().0) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    io.fd = io.fd,
+                    task.id = NonZeroU64::new(io.task.load(Ordering::Relaxed)),
+                    readable = event.is_readable(),
+                    writable = event.is_writable(),
+                    "io ready",
+                );
+                let _ = io.sender.unbounded_send(event.clone());
+                ready += 1;
             }
         }
+        self.metrics.ready_count.fetch_add(ready, Ordering::Relaxed);
+
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if let Some(uring) = &self.uring {
+            uring.complete();
+        }
     }
 }
 
 impl Handle {
-    pub(crate) fn add_source<S: Source>(
+    pub(crate) fn add_source<S: IoSource>(
         &self,
         source: &mut S,
         interests: Interest,
     ) -> io::Result<(Token, UnboundedReceiver<Event>)> {
         let (sender, receiver) = mpsc::unbounded();
-        let token = Token(self.wakers.insert(sender).unwrap());
+        let io = ScheduledIo {
+            sender,
+            #[cfg(feature = "tracing")]
+            fd: source.fd(),
+            #[cfg(feature = "tracing")]
+            task: AtomicU64::new(0),
+        };
+        let token = {
+            let mut tokens = self.tokens.lock().unwrap();
+            let Some(tokens) = tokens.as_mut() else {
+                return Err(io::Error::other("the runtime has been shut down"));
+            };
+            let token = self.wakers.insert(io).unwrap();
+            tokens.insert(token);
+            Token(token)
+        };
 
-        self.registry.register(source, token, interests)?;
+        if let Err(e) = self.registry.register(source, token, interests) {
+            self.remove(token);
+            return Err(e);
+        }
+        self.metrics.source_count.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(io.fd = source.fd(), io.token = token.0, "io registered");
 
         Ok((token, receiver))
     }
 
-    pub(crate) fn deregister_source<S: Source>(
+    pub(crate) fn deregister_source<S: IoSource>(
         &self,
         source: &mut S,
         token: Token,
     ) -> io::Result<()> {
         self.registry.deregister(source)?;
-        self.wakers.remove(token.0);
+        if self.remove(token) {
+            self.metrics.source_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(io.fd = source.fd(), io.token = token.0, "io deregistered");
 
         Ok(())
     }
+
+    /// Record the task waiting for an event of the source, reported by
+    /// the "io ready" event.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn set_waiting_task(&self, token: Token, task: Option<crate::task::Id>) {
+        if let Some(io) = self.wakers.get(token.0) {
+            let id = task.map_or(0, crate::task::Id::as_u64);
+            io.task.store(id, Ordering::Relaxed);
+        }
+    }
+
+    /// Remove a source, returns `false` if already removed on shutdown.
+    fn remove(&self, token: Token) -> bool {
+        let removed = self
+            .tokens
+            .lock()
+            .unwrap()
+            .as_mut()
+            .is_some_and(|tokens| tokens.remove(&token.0));
+        // Dropped out of the lock, the sender wakes up the registration.
+        if removed {
+            drop(self.wakers.take(token.0));
+        }
+        removed
+    }
+
+    /// Drop the sources when the runtime shuts down. Their registrations
+    /// stop receiving events, and the tasks waiting on them are woken up,
+    /// which drops them. Those tasks would otherwise keep the runtime alive.
+    pub(crate) fn shutdown(&self) {
+        let Some(tokens) = self.tokens.lock().unwrap().take() else {
+            return;
+        };
+        let sources: Vec<_> = tokens
+            .into_iter()
+            .filter_map(|token| self.wakers.take(token))
+            .collect();
+        self.metrics
+            .source_count
+            .fetch_sub(sources.len(), Ordering::Relaxed);
+        drop(sources);
+
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if let Some(uring) = &self.uring {
+            uring.shutdown();
+        }
+    }
+
+    /// Wake up the driver blocked in polling.
+    pub(crate) fn unpark(&self) {
+        self.waker.wake().expect("failed to wake up the driver");
+    }
+
+    pub(crate) fn metrics(&self) -> &DriverMetrics {
+        &self.metrics
+    }
+
+    /// Returns the `io_uring` instance, `None` if the kernel lacks support.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub(crate) fn uring(&self) -> Option<&Arc<Uring>> {
+        self.uring.as_ref()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Handle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl DriverMetrics {
+    pub(crate) fn source_count(&self) -> usize {
+        self.source_count.load(Ordering::Relaxed)
+    }
 }
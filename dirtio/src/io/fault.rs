@@ -0,0 +1,147 @@
+//! Faults injected into the IO of the sockets, set by
+//! `Builder::inject_io_faults`.
+
+use crate::runtime::rng::Rng;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Faults injected into the IO of the sockets, to exercise the paths a
+/// reliable network rarely takes, such as partial reads.
+///
+/// Every IO operation rolls the faults from a RNG seeded with `seed`, so a
+/// failure replays from the same seed as long as the operations run in the
+/// same order. No fault is injected by default.
+///
+/// The faults apply to the readiness based IO of `dirtio::net`, not to the
+/// `io-uring` operations nor to the simulated network.
+#[derive(Clone, Debug)]
+pub struct IoFaults {
+    seed: u64,
+    would_block: f64,
+    short_io: f64,
+    connection_reset: f64,
+    delay: f64,
+    max_delay: Duration,
+}
+
+impl IoFaults {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            would_block: 0.0,
+            short_io: 0.0,
+            connection_reset: 0.0,
+            delay: 0.0,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Probability of a spurious `WouldBlock`, the operation is retried
+    /// after the task yields.
+    ///
+    /// The task sees a pending poll and is woken right away, the operation
+    /// never returns the error. The readiness is kept, unlike on a real
+    /// `WouldBlock`: the socket wasn't drained, so an edge triggered source
+    /// would report no new event and the task would stall.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `0.0..=1.0`.
+    pub fn would_block(mut self, p: f64) -> Self {
+        self.would_block = probability(p);
+        self
+    }
+
+    /// Probability of a short read or write, which transfers at least one
+    /// byte but less than the buffer holds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `0.0..=1.0`.
+    pub fn short_io(mut self, p: f64) -> Self {
+        self.short_io = probability(p);
+        self
+    }
+
+    /// Probability of failing the operation with `ConnectionReset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `0.0..=1.0`.
+    pub fn connection_reset(mut self, p: f64) -> Self {
+        self.connection_reset = probability(p);
+        self
+    }
+
+    /// Probability of delaying the readiness up to `max`, on the clock of
+    /// the runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `0.0..=1.0`.
+    pub fn delay(mut self, p: f64, max: Duration) -> Self {
+        self.delay = probability(p);
+        self.max_delay = max;
+        self
+    }
+}
+
+fn probability(p: f64) -> f64 {
+    assert!((0.0..=1.0).contains(&p), "probability must be in 0.0..=1.0");
+    p
+}
+
+/// A fault injected before an operation.
+pub(crate) enum Fault {
+    WouldBlock,
+    ConnectionReset,
+    Delay(Duration),
+}
+
+/// The faults of a runtime.
+pub(crate) struct Faults {
+    config: IoFaults,
+    rng: Mutex<Rng>,
+}
+
+impl Faults {
+    pub(crate) fn new(config: IoFaults) -> Self {
+        Self {
+            rng: Mutex::new(Rng::new(config.seed)),
+            config,
+        }
+    }
+
+    /// Roll the fault of an operation about to run, if any.
+    pub(crate) fn roll(&self) -> Option<Fault> {
+        let mut rng = self.rng.lock().unwrap();
+        if rng.chance(self.config.connection_reset) {
+            return Some(Fault::ConnectionReset);
+        }
+        if rng.chance(self.config.would_block) {
+            return Some(Fault::WouldBlock);
+        }
+        if rng.chance(self.config.delay) {
+            let max = self.config.max_delay.as_nanos() as u64;
+            let delay = rng.next_u64() % (max + 1);
+            return Some(Fault::Delay(Duration::from_nanos(delay)));
+        }
+        None
+    }
+
+    /// Length of a buffer to read or write, shortened on a short IO. Never
+    /// shortened to zero, which would read as the end of the stream.
+    pub(crate) fn short_len(&self, len: usize) -> usize {
+        let mut rng = self.rng.lock().unwrap();
+        if len > 1 && rng.chance(self.config.short_io) {
+            1 + rng.below(len - 1)
+        } else {
+            len
+        }
+    }
+}
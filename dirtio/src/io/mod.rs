@@ -1,2 +1,29 @@
 pub(crate) mod driver;
+#[cfg(feature = "test-util")]
+pub(crate) mod fault;
 pub(crate) mod registration;
+pub(crate) mod resource;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub(crate) mod uring;
+
+use mio::event::Source;
+
+/// A source which can be registered on the IO driver.
+pub(crate) trait IoSource: Source {
+    /// Number of the fd or socket, for diagnostics.
+    fn fd(&self) -> i64;
+}
+
+#[cfg(unix)]
+impl<T: Source + std::os::unix::io::AsRawFd> IoSource for T {
+    fn fd(&self) -> i64 {
+        self.as_raw_fd() as i64
+    }
+}
+
+#[cfg(windows)]
+impl<T: Source + std::os::windows::io::AsRawSocket> IoSource for T {
+    fn fd(&self) -> i64 {
+        self.as_raw_socket() as i64
+    }
+}
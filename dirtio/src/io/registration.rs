@@ -1,21 +1,28 @@
-use crate::runtime::context;
+#[cfg(feature = "test-util")]
+use super::fault::Fault;
+use super::resource::Resource;
+use super::IoSource;
+
+use crate::runtime::{context, coop, trace};
 use crate::runtime::scheduler::handle::Handle;
+#[cfg(feature = "test-util")]
+use crate::runtime::time::TimerKey;
 
 use std::io;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::task::{ready, Context, Poll};
 
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::{Future, StreamExt};
-use mio::event::{Event, Source};
+use mio::event::Event;
 use mio::{Interest, Token};
 
 pub(crate) struct IoRegistration<S>
 where
-    S: Source,
+    S: IoSource,
 {
     io: Option<S>,
     registration: Registration,
@@ -23,7 +30,7 @@ where
 
 impl<S> IoRegistration<S>
 where
-    S: Source,
+    S: IoSource,
 {
     pub fn new(mut io: S) -> io::Result<Self> {
         let registration = Registration::new(&mut io, Interest::READABLE | Interest::WRITABLE)?;
@@ -40,7 +47,7 @@ where
 
 impl<S> Deref for IoRegistration<S>
 where
-    S: Source,
+    S: IoSource,
 {
     type Target = S;
 
@@ -51,7 +58,7 @@ where
 
 impl<S> Drop for IoRegistration<S>
 where
-    S: Source,
+    S: IoSource,
 {
     fn drop(&mut self) {
         let _ = self.registration.deregister(self.io.as_mut().unwrap());
@@ -95,19 +102,39 @@ impl Ready {
 
 pub(crate) struct Registration {
     token: Token,
+    /// Index of the driver the source is registered on.
+    driver: usize,
     event_receiver: Mutex<UnboundedReceiver<Event>>,
     ready: Ready,
+    /// Stats and their key for the console, `None` unless enabled.
+    resource: Option<(Arc<Resource>, u64)>,
+    /// Timers of the delayed readiness, for reading and writing.
+    #[cfg(feature = "test-util")]
+    delays: Mutex<[Option<TimerKey>; 2]>,
     handle: Handle,
 }
 
 impl Registration {
-    pub(crate) fn new(io: &mut impl Source, interests: Interest) -> io::Result<Self> {
+    pub(crate) fn new<S: IoSource>(io: &mut S, interests: Interest) -> io::Result<Self> {
         let handle = context::current();
-        let (token, event_receiver) = handle.driver.add_source(io, interests)?;
+        let driver = handle.pick_driver();
+        let (token, event_receiver) = handle.drivers[driver].add_source(io, interests)?;
+
+        let resource = handle.resources.as_ref().map(|resources| {
+            let kind = std::any::type_name::<S>().rsplit("::").next().unwrap_or("");
+            let resource = Arc::new(Resource::new(io.fd(), kind, driver));
+            let key = resources.insert(resource.clone());
+            (resource, key)
+        });
+
         Ok(Self {
             token,
+            driver,
             event_receiver: Mutex::new(event_receiver),
             ready: Ready::new(),
+            resource,
+            #[cfg(feature = "test-util")]
+            delays: Mutex::default(),
             handle,
         })
     }
@@ -119,6 +146,8 @@ impl Registration {
     ) -> io::Result<R> {
         loop {
             self.async_readiness(interest).await;
+            #[cfg(feature = "test-util")]
+            futures::future::poll_fn(|cx| self.poll_fault(cx, interest)).await?;
 
             match f() {
                 // If the result is a `WouldBlock`, clear the readiness
@@ -139,6 +168,8 @@ impl Registration {
     ) -> Poll<io::Result<R>> {
         loop {
             ready!(self.readiness(interest).poll_inner(cx));
+            #[cfg(feature = "test-util")]
+            ready!(self.poll_fault(cx, interest))?;
 
             match f() {
                 // If the result is a `WouldBlock`, clear the readiness
@@ -151,28 +182,116 @@ impl Registration {
         }
     }
 
-    pub(crate) fn deregister(&self, source: &mut impl Source) -> io::Result<()> {
-        self.handle.driver.deregister_source(source, self.token)
+    /// Length of a buffer to read or write, shortened at random when the
+    /// faults are injected.
+    pub(crate) fn io_len(&self, len: usize) -> usize {
+        #[cfg(feature = "test-util")]
+        if let Some(faults) = &self.handle.faults {
+            return faults.short_len(len);
+        }
+        len
+    }
+
+    /// Inject a fault before an operation, pending on a spurious
+    /// `WouldBlock` or a delayed readiness.
+    ///
+    /// A delayed operation waits for its timer, then runs without rolling
+    /// the faults again.
+    #[cfg(feature = "test-util")]
+    fn poll_fault(&self, cx: &mut Context<'_>, interest: Interest) -> Poll<io::Result<()>> {
+        let Some(faults) = &self.handle.faults else {
+            return Poll::Ready(Ok(()));
+        };
+        let mut delays = self.delays.lock().unwrap();
+        let delay = &mut delays[!interest.is_readable() as usize];
+        if let Some(key) = *delay {
+            let deadline = key.0;
+            if self.handle.timers.now() < deadline {
+                // Woken early, wait again with the waker of this poll.
+                *delay = Some(self.handle.register_timer(Some(key), deadline, cx.waker()));
+                return Poll::Pending;
+            }
+            // In case the poll came before the timer fired.
+            self.handle.timers.remove(key);
+            *delay = None;
+            return Poll::Ready(Ok(()));
+        }
+
+        match faults.roll() {
+            None => Poll::Ready(Ok(())),
+            Some(Fault::ConnectionReset) => Poll::Ready(Err(io::ErrorKind::ConnectionReset.into())),
+            // A yield, the readiness is kept as the source isn't drained.
+            Some(Fault::WouldBlock) => {
+                self.count_would_block();
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(Fault::Delay(duration)) => {
+                let deadline = self.handle.timers.now() + duration;
+                *delay = Some(self.handle.register_timer(None, deadline, cx.waker()));
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns the `io_uring` instance, `None` if the kernel lacks support.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub(crate) fn uring(&self) -> Option<std::sync::Arc<crate::io::uring::Uring>> {
+        self.handle.drivers[self.driver].uring().cloned()
+    }
+
+    pub(crate) fn deregister(&self, source: &mut impl IoSource) -> io::Result<()> {
+        self.handle.drivers[self.driver]
+            .deregister_source(source, self.token)
     }
 
     async fn async_readiness(&self, interest: Interest) {
         self.readiness(interest).await;
     }
 
-    fn readiness(&self, interest: Interest) -> Readiness {
+    fn readiness(&self, interest: Interest) -> Readiness<'_> {
         Readiness {
             registration: self,
             interest,
         }
     }
 
+    #[cfg(feature = "tracing")]
+    fn set_waiting_task(&self) {
+        self.handle.drivers[self.driver].set_waiting_task(self.token, context::current_task());
+    }
+
+    fn count_would_block(&self) {
+        if let Some((resource, _)) = &self.resource {
+            resource.would_block.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     fn clear_readiness(&self, interest: Interest) {
+        self.count_would_block();
         self.ready.clear(interest);
     }
 
     fn poll_event(&self, cx: &mut Context<'_>) -> Poll<Event> {
         let mut event_receiver = self.event_receiver.lock().unwrap();
-        Poll::Ready(ready!(event_receiver.poll_next_unpin(cx)).expect("channel disconnected"))
+        match ready!(event_receiver.poll_next_unpin(cx)) {
+            Some(event) => Poll::Ready(event),
+            // The runtime has shut down, no event will come. The task is
+            // dropped along with its waker.
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let (Some(resources), Some((_, key))) = (&self.handle.resources, &self.resource) {
+            resources.remove(*key);
+        }
+        #[cfg(feature = "test-util")]
+        for &key in self.delays.get_mut().unwrap().iter().flatten() {
+            self.handle.timers.remove(key);
+        }
     }
 }
 
@@ -184,15 +303,35 @@ struct Readiness<'a> {
 
 impl<'a> Readiness<'a> {
     fn poll_inner(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // Yield if current task used up its budget, even if ready.
+        let mut coop = ready!(coop::poll_proceed(cx));
+
         loop {
             // Check readiness first.
             if self.registration.ready.ready(self.interest) {
+                coop.made_progress();
                 return Poll::Ready(());
             }
             // If readiness not satisfied, get an event from the driver
             // and check again.
-            let event = ready!(self.registration.poll_event(cx));
+            let event = match self.registration.poll_event(cx) {
+                Poll::Ready(event) => event,
+                Poll::Pending => {
+                    #[cfg(feature = "tracing")]
+                    self.registration.set_waiting_task();
+                    return Poll::Pending;
+                }
+            };
             self.registration.ready.set(&event);
+
+            if let Some((resource, _)) = &self.registration.resource {
+                if event.is_readable() {
+                    resource.read_ready.fetch_add(1, Ordering::Relaxed);
+                }
+                if event.is_writable() {
+                    resource.write_ready.fetch_add(1, Ordering::Relaxed);
+                }
+            }
         }
     }
 }
@@ -201,6 +340,14 @@ impl<'a> Future for Readiness<'a> {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.poll_inner(cx)
+        let res = self.poll_inner(cx);
+        if res.is_pending() {
+            trace::leaf(if self.interest.is_readable() {
+                "io readiness (readable)"
+            } else {
+                "io readiness (writable)"
+            });
+        }
+        res
     }
 }
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Stats of a registered IO source.
+pub(crate) struct Resource {
+    pub(crate) fd: i64,
+    /// Type of the source, such as `TcpStream`.
+    pub(crate) kind: &'static str,
+    /// Index of the driver the source is registered on.
+    pub(crate) driver: usize,
+    pub(crate) read_ready: AtomicU64,
+    pub(crate) write_ready: AtomicU64,
+    /// Number of operations which returned `WouldBlock`.
+    pub(crate) would_block: AtomicU64,
+}
+
+/// The registered IO sources of a runtime.
+#[derive(Default)]
+pub(crate) struct Resources {
+    next_key: AtomicU64,
+    resources: Mutex<HashMap<u64, Arc<Resource>>>,
+}
+
+impl Resource {
+    pub(crate) fn new(fd: i64, kind: &'static str, driver: usize) -> Self {
+        Self {
+            fd,
+            kind,
+            driver,
+            read_ready: AtomicU64::new(0),
+            write_ready: AtomicU64::new(0),
+            would_block: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Resources {
+    /// Returns the key to remove the resource.
+    pub(crate) fn insert(&self, resource: Arc<Resource>) -> u64 {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        self.resources.lock().unwrap().insert(key, resource);
+        key
+    }
+
+    pub(crate) fn remove(&self, key: u64) {
+        self.resources.lock().unwrap().remove(&key);
+    }
+
+    /// Snapshot the resources, ordered by fd.
+    pub(crate) fn snapshot(&self) -> Vec<Arc<Resource>> {
+        let mut resources: Vec<_> = self.resources.lock().unwrap().values().cloned().collect();
+        resources.sort_by_key(|resource| resource.fd);
+        resources
+    }
+}
@@ -0,0 +1,374 @@
+use crate::runtime::trace;
+
+use std::any::Any;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures::Future;
+use io_uring::{opcode, squeue, types, IoUring, Probe};
+use slab::Slab;
+
+/// Number of submission queue entries.
+const ENTRIES: u32 = 256;
+
+/// User data of the cancel entries, the slab never hands it out.
+const CANCEL: u64 = u64::MAX;
+
+/// Completion based IO backed by `io_uring`.
+pub(crate) struct Uring {
+    inner: Mutex<Inner>,
+    fd: RawFd,
+}
+
+struct Inner {
+    ring: IoUring,
+    ops: Slab<Lifecycle>,
+}
+
+/// State of an in-flight operation.
+enum Lifecycle {
+    /// Submitted, but never polled.
+    Submitted,
+    /// Polled and waiting for the completion.
+    Waiting(Waker),
+    /// The `Op` was dropped before completion, its resources are kept
+    /// alive until the kernel is done with them.
+    Ignored(#[allow(dead_code)] Box<dyn Any + Send>),
+    /// Completed with the result.
+    Completed(i32),
+    /// Never submitted, the entry could not be pushed.
+    Failed(io::Error),
+}
+
+impl Uring {
+    /// Set up a ring, returns `None` if the kernel lacks support.
+    pub(crate) fn new() -> Option<Self> {
+        let ring = IoUring::new(ENTRIES).ok()?;
+
+        let mut probe = Probe::new();
+        ring.submitter().register_probe(&mut probe).ok()?;
+        let supported = [
+            opcode::Read::CODE,
+            opcode::Write::CODE,
+            opcode::Recv::CODE,
+            opcode::Send::CODE,
+            opcode::Fsync::CODE,
+            opcode::AsyncCancel::CODE,
+        ]
+        .into_iter()
+        .all(|code| probe.is_supported(code));
+        if !supported {
+            return None;
+        }
+
+        Some(Self {
+            fd: ring.as_raw_fd(),
+            inner: Mutex::new(Inner {
+                ring,
+                ops: Slab::new(),
+            }),
+        })
+    }
+
+    /// Push an entry to the submission queue.
+    ///
+    /// Entries are not submitted here, the driver submits all of them
+    /// in a batch on its next turn.
+    ///
+    /// If the submission queue can't be flushed to make room, the
+    /// operation fails with the error.
+    ///
+    /// # Safety
+    ///
+    /// Resources referenced by the entry must be valid until completion.
+    unsafe fn push(&self, entry: squeue::Entry) -> usize {
+        let mut wakers = Vec::new();
+        let mut inner = self.inner.lock().unwrap();
+        let index = inner.ops.insert(Lifecycle::Submitted);
+        let entry = entry.user_data(index as u64);
+
+        while inner.ring.submission().push(&entry).is_err() {
+            // The submission queue is full, flush it to make room.
+            match inner.ring.submit() {
+                Ok(_) => {}
+                // The completion queue is overflowed, reap it and retry.
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::ResourceBusy && inner.reap(&mut wakers) => {}
+                Err(e) => {
+                    inner.ops[index] = Lifecycle::Failed(e);
+                    break;
+                }
+            }
+        }
+        drop(inner);
+
+        for waker in wakers {
+            waker.wake();
+        }
+        index
+    }
+
+    /// Submit all the queued entries.
+    pub(crate) fn submit(&self) {
+        let inner = self.inner.lock().unwrap();
+        match inner.ring.submit() {
+            Ok(_) => {}
+            // The completion queue is overflowed, retry on next turn
+            // after the completions are reaped.
+            Err(ref e) if e.kind() == io::ErrorKind::ResourceBusy => {}
+            Err(e) => panic!("failed to submit io_uring entries: {}", e),
+        }
+    }
+
+    /// Reap the completions and wake up the waiting operations.
+    pub(crate) fn complete(&self) {
+        let mut wakers = Vec::new();
+        let mut inner = self.inner.lock().unwrap();
+        inner.reap(&mut wakers);
+        drop(inner);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Drop the wakers of the waiting operations when the runtime shuts
+    /// down, they keep their tasks alive.
+    pub(crate) fn shutdown(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        let wakers: Vec<_> = inner
+            .ops
+            .iter_mut()
+            .filter_map(|(_, state)| match state {
+                Lifecycle::Waiting(_) => match std::mem::replace(state, Lifecycle::Submitted) {
+                    Lifecycle::Waiting(waker) => Some(waker),
+                    _ => unreachable!(),
+                },
+                _ => None,
+            })
+            .collect();
+        // The tasks dropped with the wakers drop their operations.
+        drop(inner);
+        drop(wakers);
+    }
+}
+
+impl Inner {
+    /// Reap the completions, collects the wakers of the waiting
+    /// operations. Returns `false` if there were none.
+    fn reap(&mut self, wakers: &mut Vec<Waker>) -> bool {
+        let Inner { ring, ops } = self;
+
+        let mut reaped = false;
+        for cqe in ring.completion() {
+            reaped = true;
+            if cqe.user_data() == CANCEL {
+                continue;
+            }
+            let index = cqe.user_data() as usize;
+            let state = std::mem::replace(&mut ops[index], Lifecycle::Completed(cqe.result()));
+            match state {
+                Lifecycle::Submitted => {}
+                Lifecycle::Waiting(waker) => wakers.push(waker),
+                Lifecycle::Ignored(_) => {
+                    ops.remove(index);
+                }
+                Lifecycle::Completed(_) | Lifecycle::Failed(_) => {
+                    unreachable!("operation completed twice")
+                }
+            }
+        }
+        reaped
+    }
+}
+
+impl AsRawFd for Uring {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// Resources owned by an in-flight operation.
+pub(crate) trait Completable {
+    type Output;
+
+    fn complete(self, res: io::Result<u32>) -> Self::Output;
+}
+
+/// An in-flight operation.
+pub(crate) struct Op<T>
+where
+    T: Completable + Send + 'static,
+{
+    uring: Arc<Uring>,
+    index: usize,
+    data: Option<T>,
+}
+
+impl<T> Op<T>
+where
+    T: Completable + Send + 'static,
+{
+    /// # Safety
+    ///
+    /// The entry must only reference resources owned by `data`.
+    unsafe fn submit(uring: Arc<Uring>, data: T, entry: squeue::Entry) -> Self {
+        let index = uring.push(entry);
+        Self {
+            uring,
+            index,
+            data: Some(data),
+        }
+    }
+}
+
+impl<T> Future for Op<T>
+where
+    T: Completable + Send + Unpin + 'static,
+{
+    type Output = T::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.uring.inner.lock().unwrap();
+        let state = &mut inner.ops[self.index];
+
+        match state {
+            Lifecycle::Submitted | Lifecycle::Waiting(_) => {
+                *state = Lifecycle::Waiting(cx.waker().clone());
+                trace::leaf("io_uring operation");
+                Poll::Pending
+            }
+            Lifecycle::Completed(_) | Lifecycle::Failed(_) => {
+                let res = match inner.ops.remove(self.index) {
+                    Lifecycle::Completed(res) if res < 0 => Err(io::Error::from_raw_os_error(-res)),
+                    Lifecycle::Completed(res) => Ok(res as u32),
+                    Lifecycle::Failed(e) => Err(e),
+                    _ => unreachable!(),
+                };
+                drop(inner);
+
+                let data = self.data.take().expect("polled after completion");
+                Poll::Ready(data.complete(res))
+            }
+            Lifecycle::Ignored(_) => unreachable!("polled an ignored operation"),
+        }
+    }
+}
+
+impl<T> Drop for Op<T>
+where
+    T: Completable + Send + 'static,
+{
+    fn drop(&mut self) {
+        let Some(data) = self.data.take() else {
+            return;
+        };
+
+        let mut inner = self.uring.inner.lock().unwrap();
+        match inner.ops[self.index] {
+            Lifecycle::Completed(_) | Lifecycle::Failed(_) => {
+                inner.ops.remove(self.index);
+            }
+            _ => {
+                inner.ops[self.index] = Lifecycle::Ignored(Box::new(data));
+                // Cancel the operation, a receive left in flight would
+                // take the data of the next one.
+                let cancel = opcode::AsyncCancel::new(self.index as u64)
+                    .build()
+                    .user_data(CANCEL);
+                // Safety: no resources are referenced.
+                if unsafe { inner.ring.submission().push(&cancel) }.is_err() {
+                    let _ = inner.ring.submit();
+                    let _ = unsafe { inner.ring.submission().push(&cancel) };
+                }
+                // The entries may still sit in the submission queue, submit
+                // them before the caller gets a chance to close the fd.
+                let _ = inner.ring.submit();
+            }
+        }
+    }
+}
+
+/// Reads or receives into an owned buffer.
+pub(crate) struct Read {
+    buf: Vec<u8>,
+}
+
+impl Completable for Read {
+    type Output = (io::Result<usize>, Vec<u8>);
+
+    fn complete(self, res: io::Result<u32>) -> Self::Output {
+        (res.map(|n| n as usize), self.buf)
+    }
+}
+
+/// Writes or sends from an owned buffer.
+pub(crate) struct Write {
+    buf: Vec<u8>,
+}
+
+impl Completable for Write {
+    type Output = (io::Result<usize>, Vec<u8>);
+
+    fn complete(self, res: io::Result<u32>) -> Self::Output {
+        (res.map(|n| n as usize), self.buf)
+    }
+}
+
+/// Flushes a file to disk.
+pub(crate) struct Fsync;
+
+impl Completable for Fsync {
+    type Output = io::Result<()>;
+
+    fn complete(self, res: io::Result<u32>) -> Self::Output {
+        res.map(|_| ())
+    }
+}
+
+fn buf_len(buf: &[u8]) -> u32 {
+    buf.len().min(u32::MAX as usize) as u32
+}
+
+impl Op<Read> {
+    pub(crate) fn read_at(uring: Arc<Uring>, fd: RawFd, mut buf: Vec<u8>, offset: u64) -> Self {
+        let entry = opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf_len(&buf))
+            .offset(offset)
+            .build();
+        // Safety: the buffer is owned by the operation.
+        unsafe { Op::submit(uring, Read { buf }, entry) }
+    }
+
+    pub(crate) fn recv(uring: Arc<Uring>, fd: RawFd, mut buf: Vec<u8>) -> Self {
+        let entry = opcode::Recv::new(types::Fd(fd), buf.as_mut_ptr(), buf_len(&buf)).build();
+        // Safety: the buffer is owned by the operation.
+        unsafe { Op::submit(uring, Read { buf }, entry) }
+    }
+}
+
+impl Op<Write> {
+    pub(crate) fn write_at(uring: Arc<Uring>, fd: RawFd, buf: Vec<u8>, offset: u64) -> Self {
+        let entry = opcode::Write::new(types::Fd(fd), buf.as_ptr(), buf_len(&buf))
+            .offset(offset)
+            .build();
+        // Safety: the buffer is owned by the operation.
+        unsafe { Op::submit(uring, Write { buf }, entry) }
+    }
+
+    pub(crate) fn send(uring: Arc<Uring>, fd: RawFd, buf: Vec<u8>) -> Self {
+        let entry = opcode::Send::new(types::Fd(fd), buf.as_ptr(), buf_len(&buf)).build();
+        // Safety: the buffer is owned by the operation.
+        unsafe { Op::submit(uring, Write { buf }, entry) }
+    }
+}
+
+impl Op<Fsync> {
+    pub(crate) fn fsync(uring: Arc<Uring>, fd: RawFd) -> Self {
+        let entry = opcode::Fsync::new(types::Fd(fd)).build();
+        // Safety: no resources are referenced.
+        unsafe { Op::submit(uring, Fsync, entry) }
+    }
+}
@@ -1,8 +1,19 @@
 #![allow(clippy::module_inception)]
 
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod fs;
 mod io;
+mod loom;
+#[doc(hidden)]
+pub mod macros;
 pub mod net;
 pub mod runtime;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod task;
+#[cfg(feature = "test-util")]
+pub mod test;
+pub mod time;
 
-pub use dirtio_macros::main;
+pub use dirtio_macros::{main, test};
 pub use runtime::scheduler::spawn;
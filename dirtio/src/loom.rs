@@ -0,0 +1,53 @@
+//! Synchronization primitives of the scheduler, taken from `loom` under
+//! `cfg(loom)` so that the tests can model check them.
+//!
+//! Run the model checked tests with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test -p dirtio --release --lib loom
+//! ```
+
+#[cfg(not(loom))]
+pub(crate) mod sync {
+    pub(crate) use std::sync::{Arc, Condvar, Mutex};
+
+    pub(crate) mod atomic {
+        pub(crate) use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+    }
+}
+
+#[cfg(loom)]
+pub(crate) mod sync {
+    pub(crate) use ::loom::sync::{Arc, Condvar, Mutex};
+
+    pub(crate) mod atomic {
+        pub(crate) use ::loom::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+    }
+}
+
+#[cfg(not(loom))]
+pub(crate) use crossbeam_queue::SegQueue;
+
+/// Lock based queue standing in for the lock free one, whose atomics
+/// loom can't see.
+#[cfg(loom)]
+pub(crate) struct SegQueue<T> {
+    items: sync::Mutex<std::collections::VecDeque<T>>,
+}
+
+#[cfg(loom)]
+impl<T> SegQueue<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            items: sync::Mutex::new(std::collections::VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn push(&self, item: T) {
+        self.items.lock().unwrap().push_back(item);
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        self.items.lock().unwrap().pop_front()
+    }
+}
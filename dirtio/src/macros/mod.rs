@@ -0,0 +1,55 @@
+//! `select!`, `join!` and `try_join!`, exported at the root of the crate.
+
+#[doc(hidden)]
+pub mod support;
+
+/// Wait on several futures at once, and run the handler of the first one
+/// to complete. The other futures are dropped, which cancels them.
+///
+/// Each branch is `<pattern> = <future>, if <precondition> => <handler>`,
+/// the precondition is optional:
+///
+/// 1. The preconditions are evaluated, a branch whose precondition is
+///    `false` is disabled.
+/// 2. The futures of all branches are created, then the ones of the enabled
+///    branches are polled, starting from a random branch. With `biased;`
+///    before the branches, they're polled in order.
+/// 3. When a future completes, its output is matched against the pattern
+///    of the branch. On a match, the futures are dropped and the handler
+///    runs with the bindings of the pattern. Otherwise, the branch is
+///    disabled and the others are still polled.
+/// 4. Once all branches are disabled, the `else => <expression>` branch is
+///    evaluated, `select!` panics if there is none.
+///
+/// The handlers run in the enclosing async code, they may `.await`,
+/// `break`, `continue` or `return`. The futures need not be fused, nor
+/// `Unpin`.
+#[macro_export]
+macro_rules! select {
+    ($($tt:tt)*) => {
+        $crate::macros::support::select!($crate; $($tt)*)
+    };
+}
+
+/// Wait on several futures concurrently, returns the tuple of their
+/// outputs once all of them completed.
+///
+/// The futures run on the current task, use `spawn` to run them in
+/// parallel.
+#[macro_export]
+macro_rules! join {
+    ($($tt:tt)*) => {
+        $crate::macros::support::join!($crate; $($tt)*)
+    };
+}
+
+/// Wait on several futures returning a `Result` concurrently, returns the
+/// tuple of their values once all of them succeeded, or the first error.
+///
+/// The other futures are dropped on an error, which cancels them.
+#[macro_export]
+macro_rules! try_join {
+    ($($tt:tt)*) => {
+        $crate::macros::support::try_join!($crate; $($tt)*)
+    };
+}
@@ -0,0 +1,13 @@
+//! Items used by the expansion of the macros, not a public API.
+
+pub use dirtio_macros::{join, select, try_join};
+
+pub use futures::future::maybe_done;
+
+pub use std::future::{poll_fn, Future, IntoFuture};
+pub use std::task::Poll;
+
+/// Returns a number in `0..n`, the branch `select!` polls first.
+pub fn thread_rng_n(n: usize) -> usize {
+    crate::runtime::rng::thread_below(n)
+}
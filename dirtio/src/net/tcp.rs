@@ -1,48 +1,180 @@
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::io::uring::Op;
 use crate::io::registration::IoRegistration;
+#[cfg(feature = "sim")]
+use crate::sim;
 
 use std::io::{self, Read, Write};
 use std::net::SocketAddr;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use std::os::unix::io::AsRawFd;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+#[cfg(feature = "sim")]
+use futures::future::poll_fn;
 use futures::{AsyncRead, AsyncWrite};
 use mio::Interest;
 
 pub struct TcpListener {
-    io: IoRegistration<mio::net::TcpListener>,
+    io: ListenerIo,
+}
+
+enum ListenerIo {
+    Mio(IoRegistration<mio::net::TcpListener>),
+    /// In the network of a simulation.
+    #[cfg(feature = "sim")]
+    Sim(sim::net::TcpListener),
 }
 
 impl TcpListener {
     pub async fn accept(&mut self) -> io::Result<(TcpStream, SocketAddr)> {
-        let (stream, addr) = self
-            .io
-            .registration()
-            .async_io(Interest::READABLE, || self.io.accept())
-            .await?;
-        Ok((TcpStream::from_mio(stream)?, addr))
+        match &self.io {
+            ListenerIo::Mio(io) => {
+                let (stream, addr) = io
+                    .registration()
+                    .async_io(Interest::READABLE, || io.accept())
+                    .await?;
+                Ok((TcpStream::from_mio(stream)?, addr))
+            }
+            #[cfg(feature = "sim")]
+            ListenerIo::Sim(io) => {
+                let (stream, addr) = poll_fn(|cx| io.poll_accept(cx)).await;
+                let stream = TcpStream {
+                    io: StreamIo::Sim(stream),
+                };
+                Ok((stream, addr))
+            }
+        }
     }
 
     pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        #[cfg(feature = "sim")]
+        if let Some(handle) = sim::net::current() {
+            let io = sim::net::TcpListener::bind(handle, addr)?;
+            return Ok(Self {
+                io: ListenerIo::Sim(io),
+            });
+        }
+
         Ok(Self {
-            io: IoRegistration::new(mio::net::TcpListener::bind(addr)?)?,
+            io: ListenerIo::Mio(IoRegistration::new(mio::net::TcpListener::bind(addr)?)?),
         })
     }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match &self.io {
+            ListenerIo::Mio(io) => io.local_addr(),
+            #[cfg(feature = "sim")]
+            ListenerIo::Sim(io) => Ok(io.local_addr()),
+        }
+    }
 }
 
 pub struct TcpStream {
-    io: IoRegistration<mio::net::TcpStream>,
+    io: StreamIo,
+}
+
+enum StreamIo {
+    Mio(IoRegistration<mio::net::TcpStream>),
+    /// In the network of a simulation.
+    #[cfg(feature = "sim")]
+    Sim(sim::net::TcpStream),
 }
 
 impl TcpStream {
     pub fn connect(addr: SocketAddr) -> io::Result<Self> {
+        #[cfg(feature = "sim")]
+        if let Some(handle) = sim::net::current() {
+            let io = sim::net::TcpStream::connect(handle, addr)?;
+            return Ok(Self {
+                io: StreamIo::Sim(io),
+            });
+        }
+
         Ok(Self {
-            io: IoRegistration::new(mio::net::TcpStream::connect(addr)?)?,
+            io: StreamIo::Mio(IoRegistration::new(mio::net::TcpStream::connect(addr)?)?),
         })
     }
 
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match &self.io {
+            StreamIo::Mio(io) => io.local_addr(),
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => Ok(io.local_addr()),
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match &self.io {
+            StreamIo::Mio(io) => io.peer_addr(),
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => Ok(io.peer_addr()),
+        }
+    }
+
+    /// Reads into an owned buffer, returns the number of bytes read
+    /// along with the buffer.
+    ///
+    /// Completion based when the `io-uring` feature is enabled and
+    /// supported by the kernel.
+    pub async fn read_owned(&self, mut buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        match &self.io {
+            StreamIo::Mio(io) => {
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                if let Some(uring) = io.registration().uring() {
+                    return Op::recv(uring, io.as_raw_fd(), buf).await;
+                }
+
+                let registration = io.registration();
+                let res = registration
+                    .async_io(Interest::READABLE, || {
+                        let len = registration.io_len(buf.len());
+                        (&**io).read(&mut buf[..len])
+                    })
+                    .await;
+                (res, buf)
+            }
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => {
+                let res = poll_fn(|cx| io.poll_read(cx, &mut buf)).await;
+                (res, buf)
+            }
+        }
+    }
+
+    /// Writes from an owned buffer, returns the number of bytes written
+    /// along with the buffer.
+    ///
+    /// Completion based when the `io-uring` feature is enabled and
+    /// supported by the kernel.
+    pub async fn write_owned(&self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        match &self.io {
+            StreamIo::Mio(io) => {
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                if let Some(uring) = io.registration().uring() {
+                    return Op::send(uring, io.as_raw_fd(), buf).await;
+                }
+
+                let registration = io.registration();
+                let res = registration
+                    .async_io(Interest::WRITABLE, || {
+                        (&**io).write(&buf[..registration.io_len(buf.len())])
+                    })
+                    .await;
+                (res, buf)
+            }
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => {
+                let res = poll_fn(|cx| io.poll_write(cx, &buf)).await;
+                (res, buf)
+            }
+        }
+    }
+
     fn from_mio(io: mio::net::TcpStream) -> io::Result<Self> {
         Ok(Self {
-            io: IoRegistration::new(io)?,
+            io: StreamIo::Mio(IoRegistration::new(io)?),
         })
     }
 }
@@ -53,21 +185,40 @@ impl AsyncRead for TcpStream {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        self.io
-            .registration()
-            .poll_io(cx, Interest::READABLE, || (&*self.io).read(buf))
+        match &self.io {
+            StreamIo::Mio(io) => {
+                let registration = io.registration();
+                registration.poll_io(cx, Interest::READABLE, || {
+                    let len = registration.io_len(buf.len());
+                    (&**io).read(&mut buf[..len])
+                })
+            }
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => io.poll_read(cx, buf),
+        }
     }
 }
 
 impl AsyncWrite for TcpStream {
     fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(self.io.shutdown(std::net::Shutdown::Write))
+        match &self.io {
+            StreamIo::Mio(io) => Poll::Ready(io.shutdown(std::net::Shutdown::Write)),
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => {
+                io.shutdown();
+                Poll::Ready(Ok(()))
+            }
+        }
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.io
-            .registration()
-            .poll_io(cx, Interest::WRITABLE, || (&*self.io).flush())
+        match &self.io {
+            StreamIo::Mio(io) => io
+                .registration()
+                .poll_io(cx, Interest::WRITABLE, || (&**io).flush()),
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(_) => Poll::Ready(Ok(())),
+        }
     }
 
     fn poll_write(
@@ -75,8 +226,15 @@ impl AsyncWrite for TcpStream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        self.io
-            .registration()
-            .poll_io(cx, Interest::WRITABLE, || (&*self.io).write(buf))
+        match &self.io {
+            StreamIo::Mio(io) => {
+                let registration = io.registration();
+                registration.poll_io(cx, Interest::WRITABLE, || {
+                    (&**io).write(&buf[..registration.io_len(buf.len())])
+                })
+            }
+            #[cfg(feature = "sim")]
+            StreamIo::Sim(io) => io.poll_write(cx, buf),
+        }
     }
 }
@@ -1,50 +1,164 @@
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::io::uring::Op;
 use crate::io::registration::IoRegistration;
+#[cfg(feature = "sim")]
+use crate::sim;
 
 use std::io;
 use std::net::SocketAddr;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use std::os::unix::io::AsRawFd;
 
+#[cfg(feature = "sim")]
+use futures::future::poll_fn;
 use mio::Interest;
 
 pub struct UdpSocket {
-    io: IoRegistration<mio::net::UdpSocket>,
+    io: SocketIo,
+}
+
+enum SocketIo {
+    Mio(IoRegistration<mio::net::UdpSocket>),
+    /// In the network of a simulation.
+    #[cfg(feature = "sim")]
+    Sim(sim::net::UdpSocket),
 }
 
 impl UdpSocket {
     pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        #[cfg(feature = "sim")]
+        if let Some(handle) = sim::net::current() {
+            let io = sim::net::UdpSocket::bind(handle, addr)?;
+            return Ok(Self {
+                io: SocketIo::Sim(io),
+            });
+        }
+
         Ok(Self {
-            io: IoRegistration::new(mio::net::UdpSocket::bind(addr)?)?,
+            io: SocketIo::Mio(IoRegistration::new(mio::net::UdpSocket::bind(addr)?)?),
         })
     }
 
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match &self.io {
+            SocketIo::Mio(io) => io.local_addr(),
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => Ok(io.local_addr()),
+        }
+    }
+
     pub fn connect(&self, addr: SocketAddr) -> io::Result<()> {
-        self.io.connect(addr)
+        match &self.io {
+            SocketIo::Mio(io) => io.connect(addr),
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => {
+                io.connect(addr);
+                Ok(())
+            }
+        }
     }
 
     pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.io
-            .registration()
-            .async_io(Interest::READABLE, || self.io.recv(buf))
-            .await
+        match &self.io {
+            SocketIo::Mio(io) => {
+                io.registration()
+                    .async_io(Interest::READABLE, || io.recv(buf))
+                    .await
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => poll_fn(|cx| io.poll_recv_from(cx, buf))
+                .await
+                .map(|(n, _)| n),
+        }
     }
 
     pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.io
-            .registration()
-            .async_io(Interest::READABLE, || self.io.recv_from(buf))
-            .await
+        match &self.io {
+            SocketIo::Mio(io) => {
+                io.registration()
+                    .async_io(Interest::READABLE, || io.recv_from(buf))
+                    .await
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => poll_fn(|cx| io.poll_recv_from(cx, buf)).await,
+        }
     }
 
     pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
-        self.io
-            .registration()
-            .async_io(Interest::WRITABLE, || self.io.send(buf))
-            .await
+        match &self.io {
+            SocketIo::Mio(io) => {
+                io.registration()
+                    .async_io(Interest::WRITABLE, || io.send(buf))
+                    .await
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => poll_fn(|cx| io.poll_send(cx, buf)).await,
+        }
+    }
+
+    /// Receives into an owned buffer from the connected peer, returns the
+    /// number of bytes received along with the buffer.
+    ///
+    /// Completion based when the `io-uring` feature is enabled and
+    /// supported by the kernel.
+    pub async fn recv_owned(&self, mut buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        match &self.io {
+            SocketIo::Mio(io) => {
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                if let Some(uring) = io.registration().uring() {
+                    return Op::recv(uring, io.as_raw_fd(), buf).await;
+                }
+
+                let res = io
+                    .registration()
+                    .async_io(Interest::READABLE, || io.recv(&mut buf))
+                    .await;
+                (res, buf)
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(_) => {
+                let res = self.recv(&mut buf).await;
+                (res, buf)
+            }
+        }
+    }
+
+    /// Sends an owned buffer to the connected peer, returns the number of
+    /// bytes sent along with the buffer.
+    ///
+    /// Completion based when the `io-uring` feature is enabled and
+    /// supported by the kernel.
+    pub async fn send_owned(&self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        match &self.io {
+            SocketIo::Mio(io) => {
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                if let Some(uring) = io.registration().uring() {
+                    return Op::send(uring, io.as_raw_fd(), buf).await;
+                }
+
+                let res = io
+                    .registration()
+                    .async_io(Interest::WRITABLE, || io.send(&buf))
+                    .await;
+                (res, buf)
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => {
+                let res = poll_fn(|cx| io.poll_send(cx, &buf)).await;
+                (res, buf)
+            }
+        }
     }
 
     pub async fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
-        self.io
-            .registration()
-            .async_io(Interest::WRITABLE, || self.io.send_to(buf, target))
-            .await
+        match &self.io {
+            SocketIo::Mio(io) => {
+                io.registration()
+                    .async_io(Interest::WRITABLE, || io.send_to(buf, target))
+                    .await
+            }
+            #[cfg(feature = "sim")]
+            SocketIo::Sim(io) => poll_fn(|cx| io.poll_send_to(cx, buf, target)).await,
+        }
     }
 }
@@ -0,0 +1,78 @@
+use super::metrics::HistogramConfig;
+
+use crate::task::TaskMeta;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A hook run on the threads of the runtime.
+pub(crate) type Callback = Arc<dyn Fn() + Send + Sync>;
+
+/// A hook run on the events of a task.
+pub(crate) type TaskCallback = Arc<dyn Fn(&TaskMeta<'_>) + Send + Sync>;
+
+/// Settings of the runtime needed by the scheduler.
+#[derive(Clone, Default)]
+pub(crate) struct Config {
+    /// Run before a thread blocks waiting for work.
+    pub(crate) before_park: Option<Callback>,
+    /// Run after a thread wakes up from waiting for work.
+    pub(crate) after_unpark: Option<Callback>,
+    /// Run when a task is spawned.
+    pub(crate) on_task_spawn: Option<TaskCallback>,
+    /// Run before a task is polled.
+    pub(crate) before_task_poll: Option<TaskCallback>,
+    /// Run after a task is polled.
+    pub(crate) after_task_poll: Option<TaskCallback>,
+    /// Run when a task completes.
+    pub(crate) on_task_terminate: Option<TaskCallback>,
+    /// What to do when a task panics.
+    pub(crate) unhandled_panic: UnhandledPanic,
+    /// Buckets of the poll time histogram, `None` if disabled.
+    pub(crate) poll_time_histogram: Option<HistogramConfig>,
+    /// Report the polls taking longer, `None` if disabled.
+    pub(crate) slow_poll_threshold: Option<Duration>,
+    /// Report the pending tasks dropped with their wakers.
+    pub(crate) detect_leaked_tasks: bool,
+    /// Keep the live tasks for `Handle::dump`.
+    pub(crate) task_dump: bool,
+    /// Collect the stats of the IO sources for the console.
+    pub(crate) console: bool,
+    /// Start with the time paused, set by `Builder::start_paused` and the
+    /// simulation.
+    pub(crate) start_paused: bool,
+    /// Faults injected into the IO, `None` if disabled.
+    #[cfg(feature = "test-util")]
+    pub(crate) io_faults: Option<crate::io::fault::IoFaults>,
+    /// The runtime is a simulation, which owns its virtual clock.
+    #[cfg(feature = "sim")]
+    pub(crate) simulation: bool,
+    /// The network of the sockets, set by the simulation.
+    #[cfg(feature = "sim")]
+    pub(crate) net: Option<Arc<crate::sim::net::Net>>,
+}
+
+impl Config {
+    /// Whether the polls record the await points of the tasks, which costs
+    /// an allocation and a lock for each pending poll.
+    pub(crate) fn capture_traces(&self) -> bool {
+        self.task_dump || self.detect_leaked_tasks || self.slow_poll_threshold.is_some()
+    }
+}
+
+/// How the runtime handles a panic in a spawned task, set by
+/// `Builder::unhandled_panic`.
+///
+/// The panic is always reported to the `JoinHandle` of the task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnhandledPanic {
+    /// Keep running the other tasks.
+    #[default]
+    Ignore,
+    /// Shut down the runtime, the remaining tasks are dropped and
+    /// `block_on` panics.
+    ShutdownRuntime,
+    /// Abort the process.
+    Abort,
+}
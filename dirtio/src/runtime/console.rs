@@ -0,0 +1,147 @@
+//! Console server, streams snapshots of a runtime to `dirtio-console`.
+//!
+//! Clients connecting to the Unix socket receive a snapshot every
+//! `PUBLISH_INTERVAL`, one record per line with tab separated fields:
+//!
+//! ```text
+//! snapshot <uptime ms>
+//! worker   <index> <polls> <steals> <parks> <busy ns>
+//! task     <id> <state> <polls> <wakes> <busy ns> <spawn location> <name>
+//! resource <fd> <kind> <driver> <read ready> <write ready> <would block>
+//! end
+//! ```
+
+use super::scheduler::handle::HandleInner;
+
+use crate::io::resource::Resources;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval of the snapshots sent to the clients.
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval of accepting clients and checking the runtime is alive.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Listen on the socket at `path` in a background thread, which exits
+/// when the runtime shuts down.
+pub(crate) fn spawn(path: PathBuf, handle: Weak<HandleInner>) -> io::Result<()> {
+    // Remove the socket left by a previous process, but no other file.
+    match fs::symlink_metadata(&path) {
+        Ok(meta) if meta.file_type().is_socket() => fs::remove_file(&path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(&path)?;
+    listener.set_nonblocking(true)?;
+
+    thread::Builder::new()
+        .name("dirtio-console".to_string())
+        .spawn(move || {
+            serve(&listener, &handle);
+            let _ = fs::remove_file(&path);
+        })?;
+    Ok(())
+}
+
+fn serve(listener: &UnixListener, handle: &Weak<HandleInner>) {
+    let start = Instant::now();
+    let mut clients: Vec<UnixStream> = Vec::new();
+    let mut last_publish: Option<Instant> = None;
+
+    loop {
+        let Some(handle) = handle.upgrade() else {
+            return;
+        };
+        if handle.is_shutdown() {
+            return;
+        }
+
+        while let Ok((stream, _)) = listener.accept() {
+            let setup = stream
+                .set_nonblocking(false)
+                .and_then(|_| stream.set_write_timeout(Some(PUBLISH_INTERVAL)));
+            if setup.is_ok() {
+                clients.push(stream);
+                // Send the first snapshot right away.
+                last_publish = None;
+            }
+        }
+
+        if !clients.is_empty() && last_publish.is_none_or(|t| t.elapsed() >= PUBLISH_INTERVAL) {
+            let snapshot = snapshot(&handle, start);
+            // Drop the clients which went away or stopped reading.
+            clients.retain_mut(|client| client.write_all(snapshot.as_bytes()).is_ok());
+            last_publish = Some(Instant::now());
+        }
+
+        drop(handle);
+        thread::sleep(ACCEPT_INTERVAL);
+    }
+}
+
+fn snapshot(handle: &HandleInner, start: Instant) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "snapshot\t{}", start.elapsed().as_millis());
+
+    for (index, worker) in handle.shared.workers.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "worker\t{}\t{}\t{}\t{}\t{}",
+            index,
+            worker.poll_count.load(Ordering::Relaxed),
+            worker.steal_count.load(Ordering::Relaxed),
+            worker.park_count.load(Ordering::Relaxed),
+            worker.busy_duration.load(Ordering::Relaxed),
+        );
+    }
+
+    for task in handle.dump().tasks() {
+        let _ = writeln!(
+            out,
+            "task\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            task.id(),
+            task.state(),
+            task.polls(),
+            task.wakes(),
+            task.busy_duration().as_nanos(),
+            task.spawned_at(),
+            field(task.name().unwrap_or("")),
+        );
+    }
+
+    for resource in handle.resources.iter().flat_map(Resources::snapshot) {
+        let _ = writeln!(
+            out,
+            "resource\t{}\t{}\t{}\t{}\t{}\t{}",
+            resource.fd,
+            resource.kind,
+            resource.driver,
+            resource.read_ready.load(Ordering::Relaxed),
+            resource.write_ready.load(Ordering::Relaxed),
+            resource.would_block.load(Ordering::Relaxed),
+        );
+    }
+
+    out.push_str("end\n");
+    out
+}
+
+/// Keep a field from breaking the record.
+fn field(s: &str) -> String {
+    s.replace(['\t', '\n'], " ")
+}
@@ -1,21 +1,121 @@
-use crate::runtime::scheduler::handle::Handle;
+use crate::runtime::scheduler::handle::{Handle, HandleInner};
+use crate::runtime::TryCurrentError;
+use crate::task::Id;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
 
 thread_local! {
     /// Handle to the runtime of current thread.
-    static CONTEXT: RefCell<Option<Handle>> = RefCell::new(None);
+    static CONTEXT: RefCell<Option<Handle>> = const { RefCell::new(None) };
+
+    /// Whether current thread is driving a runtime.
+    static RUNTIME: Cell<bool> = const { Cell::new(false) };
+
+    /// The task being polled on current thread.
+    static TASK: Cell<Option<Id>> = const { Cell::new(None) };
+
+    /// The runtime and the index of the worker running on current thread.
+    static WORKER: Cell<Option<(*const HandleInner, usize)>> = const { Cell::new(None) };
 }
 
 pub(crate) fn current() -> Handle {
-    CONTEXT.with(|r| {
-        r.borrow()
-            .as_ref()
-            .expect("called outside of context")
-            .clone()
-    })
+    try_current().unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub(crate) fn try_current() -> Result<Handle, TryCurrentError> {
+    CONTEXT
+        .try_with(|r| r.borrow().clone())
+        .map_err(|_| TryCurrentError::thread_local_destroyed())?
+        .ok_or_else(TryCurrentError::no_context)
+}
+
+/// Set the runtime of current thread, the previous one is restored when
+/// the guard is dropped.
+pub(crate) fn set_current(handle: Handle) -> SetCurrentGuard {
+    let prev = CONTEXT.with(|r| r.borrow_mut().replace(handle));
+    SetCurrentGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+pub(crate) struct SetCurrentGuard {
+    prev: Option<Handle>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for SetCurrentGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        // The thread local may be destroyed when a thread exits.
+        let _ = CONTEXT.try_with(|r| *r.borrow_mut() = prev);
+    }
+}
+
+/// Mark current thread as driving a runtime until the guard is dropped.
+///
+/// # Panics
+///
+/// Panics if current thread is already driving one, blocking in it would
+/// stop the tasks from making progress.
+pub(crate) fn enter_runtime() -> EnterRuntimeGuard {
+    if RUNTIME.with(|r| r.replace(true)) {
+        panic!(
+            "cannot block on a runtime from within a runtime, \
+             the thread is being used to drive the tasks"
+        );
+    }
+
+    EnterRuntimeGuard {
+        _not_send: PhantomData,
+    }
+}
+
+pub(crate) struct EnterRuntimeGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for EnterRuntimeGuard {
+    fn drop(&mut self) {
+        let _ = RUNTIME.try_with(|r| r.set(false));
+    }
+}
+
+/// Returns the index of the worker on current thread, if it's a worker
+/// of the runtime.
+pub(crate) fn worker(runtime: &HandleInner) -> Option<usize> {
+    WORKER
+        .with(Cell::get)
+        .filter(|(ptr, _)| std::ptr::eq(*ptr, runtime))
+        .map(|(_, index)| index)
+}
+
+pub(crate) fn set_worker(runtime: &HandleInner, index: usize) {
+    WORKER.with(|w| w.set(Some((runtime, index))));
+}
+
+/// Returns the id of the task being polled on current thread.
+pub(crate) fn current_task() -> Option<Id> {
+    TASK.try_with(Cell::get).ok().flatten()
+}
+
+/// Set the task being polled on current thread until the guard is dropped.
+pub(crate) fn set_current_task(id: Id) -> SetTaskGuard {
+    let prev = TASK.with(|t| t.replace(Some(id)));
+    SetTaskGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+pub(crate) struct SetTaskGuard {
+    prev: Option<Id>,
+    _not_send: PhantomData<*const ()>,
 }
 
-pub(crate) fn set_current(handle: Handle) {
-    CONTEXT.with(|r| r.borrow_mut().replace(handle));
+impl Drop for SetTaskGuard {
+    fn drop(&mut self) {
+        let _ = TASK.try_with(|t| t.set(self.prev));
+    }
 }
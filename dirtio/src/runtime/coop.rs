@@ -0,0 +1,66 @@
+//! Cooperative scheduling.
+//!
+//! Each poll of a task gets a budget of operations, the IO resources return
+//! `Pending` once it's used up, so that the task yields to the others even
+//! if its resources are always ready.
+
+use std::cell::Cell;
+use std::task::{Context, Poll};
+
+/// Number of operations a task may perform in one poll.
+const INITIAL: u8 = 128;
+
+thread_local! {
+    /// Budget of current task, `None` if unconstrained.
+    static BUDGET: Cell<Option<u8>> = const { Cell::new(None) };
+}
+
+/// Run `f` with a fresh budget.
+pub(crate) fn budget<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(Option<u8>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            let _ = BUDGET.try_with(|b| b.set(self.0));
+        }
+    }
+
+    let _reset = Reset(BUDGET.with(|b| b.replace(Some(INITIAL))));
+    f()
+}
+
+/// Consume a unit of the budget, returns `Pending` and schedule the task to
+/// be woken up if it's used up.
+///
+/// The unit is given back if the returned guard is dropped without calling
+/// `made_progress`.
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<RestoreOnPending> {
+    BUDGET.with(|b| match b.get() {
+        None => Poll::Ready(RestoreOnPending(None)),
+        Some(0) => {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+        Some(n) => {
+            b.set(Some(n - 1));
+            Poll::Ready(RestoreOnPending(Some(n)))
+        }
+    })
+}
+
+/// Give back the budget unit unless progress was made.
+pub(crate) struct RestoreOnPending(Option<u8>);
+
+impl RestoreOnPending {
+    pub(crate) fn made_progress(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for RestoreOnPending {
+    fn drop(&mut self) {
+        if let Some(n) = self.0 {
+            let _ = BUDGET.try_with(|b| b.set(Some(n)));
+        }
+    }
+}
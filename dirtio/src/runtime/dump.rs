@@ -0,0 +1,122 @@
+use super::trace::Trace;
+
+use crate::task::Id;
+
+use std::fmt;
+use std::panic::Location;
+use std::time::Duration;
+
+/// Snapshot of the live tasks of a runtime, returned by `Handle::dump`.
+#[derive(Debug)]
+pub struct Dump {
+    pub(crate) tasks: Vec<TaskDump>,
+}
+
+impl Dump {
+    pub fn tasks(&self) -> &[TaskDump] {
+        &self.tasks
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for task in &self.tasks {
+            write!(f, "{}", task)?;
+        }
+        Ok(())
+    }
+}
+
+/// A live task in a `Dump`.
+#[derive(Debug)]
+pub struct TaskDump {
+    pub(crate) id: Id,
+    pub(crate) name: Option<String>,
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) state: TaskState,
+    pub(crate) polls: u64,
+    pub(crate) wakes: u64,
+    pub(crate) busy: Duration,
+    pub(crate) trace: Trace,
+}
+
+impl TaskDump {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn spawned_at(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    /// Number of times the task was polled.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Number of times the task was woken.
+    pub fn wakes(&self) -> u64 {
+        self.wakes
+    }
+
+    /// Total time spent polling the task.
+    pub fn busy_duration(&self) -> Duration {
+        self.busy
+    }
+
+    /// Await points at the end of the last poll of the task, empty if it
+    /// was never polled.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+}
+
+impl fmt::Display for TaskDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "task {}", self.id)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({})", name)?;
+        }
+        writeln!(f, " spawned at {}, {}", self.location, self.state)?;
+        write!(f, "{}", self.trace)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+    /// Waiting in a queue to be polled.
+    Scheduled = 0,
+    /// Waiting for a wakeup.
+    Idle = 1,
+    /// Being polled.
+    Running = 2,
+}
+
+impl TaskState {
+    pub(crate) fn from_u8(state: u8) -> Self {
+        match state {
+            0 => TaskState::Scheduled,
+            1 => TaskState::Idle,
+            _ => TaskState::Running,
+        }
+    }
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            TaskState::Scheduled => "scheduled",
+            TaskState::Idle => "idle",
+            TaskState::Running => "running",
+        };
+        f.write_str(state)
+    }
+}
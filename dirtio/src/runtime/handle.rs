@@ -0,0 +1,182 @@
+use super::context;
+use super::dump::Dump;
+use super::metrics::RuntimeMetrics;
+use super::park::ParkThread;
+use super::scheduler;
+use super::scheduler::join_handle::JoinHandle;
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use futures::{pin_mut, Future};
+
+/// Handle to a runtime, it can be cloned and used from any thread.
+#[derive(Clone)]
+pub struct Handle {
+    pub(crate) inner: scheduler::handle::Handle,
+}
+
+impl Handle {
+    /// Returns the handle of current runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the context of a runtime.
+    pub fn current() -> Self {
+        Self {
+            inner: context::current(),
+        }
+    }
+
+    /// Returns the handle of current runtime, or an error if called outside
+    /// of the context of a runtime.
+    pub fn try_current() -> Result<Self, TryCurrentError> {
+        context::try_current().map(|inner| Self { inner })
+    }
+
+    /// Enter the context of the runtime, so that `dirtio::spawn` and the IO
+    /// resources can be used on current thread. The previous context is
+    /// restored when the guard is dropped.
+    pub fn enter(&self) -> EnterGuard<'_> {
+        EnterGuard {
+            _guard: self.inner.enter(),
+            _handle: PhantomData,
+        }
+    }
+
+    /// Returns the metrics of the runtime.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        RuntimeMetrics::new(self.inner.clone())
+    }
+
+    /// Snapshot the live tasks of the runtime, with their state and the
+    /// await points at the end of their last poll.
+    ///
+    /// The await chains go through the futures wrapped by
+    /// `dirtio::task::traced`, down to the leaf futures of dirtio.
+    ///
+    /// Empty unless `Builder::enable_task_dump` was called.
+    pub fn dump(&self) -> Dump {
+        self.inner.dump()
+    }
+
+    /// Spawn a future onto the runtime.
+    #[track_caller]
+    pub fn spawn<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        self.inner.spawn(fut)
+    }
+
+    /// Run a future to completion on current thread, within the context of
+    /// the runtime.
+    ///
+    /// On a current thread runtime, the spawned tasks and the IO are not
+    /// driven by this, only by `Runtime::block_on` and `Runtime::turn`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a runtime, or if the runtime shuts
+    /// down before the future completes.
+    pub fn block_on<F>(&self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        let _runtime = context::enter_runtime();
+        let _guard = self.inner.enter();
+
+        let park_thread = ParkThread::new();
+        let unpark_thread = park_thread.unpark();
+        let _blocker = self.inner.add_blocker(unpark_thread.clone());
+
+        let waker = unpark_thread.into_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        pin_mut!(fut);
+
+        loop {
+            self.inner.check_shutdown();
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(r) => break r,
+                Poll::Pending => {
+                    park_thread.park();
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").finish_non_exhaustive()
+    }
+}
+
+/// Guard returned by `Handle::enter` and `Runtime::enter`, restores the
+/// previous context when dropped.
+#[must_use = "the context is left when the guard is dropped"]
+pub struct EnterGuard<'a> {
+    _guard: context::SetCurrentGuard,
+    _handle: PhantomData<&'a Handle>,
+}
+
+impl fmt::Debug for EnterGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnterGuard").finish_non_exhaustive()
+    }
+}
+
+/// Error returned by `Handle::try_current`.
+#[derive(Debug)]
+pub struct TryCurrentError {
+    kind: TryCurrentErrorKind,
+}
+
+#[derive(Debug)]
+enum TryCurrentErrorKind {
+    NoContext,
+    ThreadLocalDestroyed,
+}
+
+impl TryCurrentError {
+    pub(crate) fn no_context() -> Self {
+        Self {
+            kind: TryCurrentErrorKind::NoContext,
+        }
+    }
+
+    pub(crate) fn thread_local_destroyed() -> Self {
+        Self {
+            kind: TryCurrentErrorKind::ThreadLocalDestroyed,
+        }
+    }
+
+    /// Returns `true` if called outside of the context of a runtime.
+    pub fn is_missing_context(&self) -> bool {
+        matches!(self.kind, TryCurrentErrorKind::NoContext)
+    }
+
+    /// Returns `true` if the thread local of the context has been destroyed.
+    pub fn is_thread_local_destroyed(&self) -> bool {
+        matches!(self.kind, TryCurrentErrorKind::ThreadLocalDestroyed)
+    }
+}
+
+impl fmt::Display for TryCurrentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TryCurrentErrorKind::NoContext => {
+                write!(f, "called outside of the context of a dirtio runtime")
+            }
+            TryCurrentErrorKind::ThreadLocalDestroyed => {
+                write!(f, "the context of the dirtio runtime has been destroyed")
+            }
+        }
+    }
+}
+
+impl Error for TryCurrentError {}
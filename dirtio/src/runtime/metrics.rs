@@ -0,0 +1,213 @@
+use super::scheduler::handle::Handle;
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters and gauges of a runtime, returned by `Runtime::metrics`.
+///
+/// The values are read from atomics on each call, workers are indexed
+/// from `0` to `num_workers() - 1`.
+#[derive(Clone)]
+pub struct RuntimeMetrics {
+    handle: Handle,
+}
+
+impl RuntimeMetrics {
+    pub(crate) fn new(handle: Handle) -> Self {
+        Self { handle }
+    }
+
+    /// Number of workers, `1` for the current thread runtime.
+    pub fn num_workers(&self) -> usize {
+        self.handle.shared.workers.len()
+    }
+
+    /// Number of tasks spawned but not yet completed or dropped.
+    pub fn num_alive_tasks(&self) -> usize {
+        self.handle.shared.alive_tasks()
+    }
+
+    /// Number of tasks in the global queue.
+    pub fn global_queue_depth(&self) -> usize {
+        self.handle.shared.global_queue_depth()
+    }
+
+    /// Number of tasks polled by the worker.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is out of range, so for the other worker metrics.
+    pub fn worker_poll_count(&self, worker: usize) -> u64 {
+        self.worker(worker).poll_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of tasks stolen by the worker from the others, only with
+    /// sharded IO.
+    pub fn worker_steal_count(&self, worker: usize) -> u64 {
+        self.worker(worker).steal_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the worker blocked waiting for work.
+    pub fn worker_park_count(&self, worker: usize) -> u64 {
+        self.worker(worker).park_count.load(Ordering::Relaxed)
+    }
+
+    /// Total time the worker spent polling tasks.
+    pub fn worker_total_busy_duration(&self, worker: usize) -> Duration {
+        Duration::from_nanos(self.worker(worker).busy_duration.load(Ordering::Relaxed))
+    }
+
+    /// Number of IO events dispatched by the drivers.
+    pub fn io_driver_ready_count(&self) -> u64 {
+        self.handle
+            .drivers
+            .iter()
+            .map(|driver| driver.metrics().ready_count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Number of IO sources registered on the drivers.
+    pub fn io_driver_source_count(&self) -> usize {
+        self.handle
+            .drivers
+            .iter()
+            .map(|driver| driver.metrics().source_count())
+            .sum()
+    }
+
+    /// Returns `true` if the poll time histogram is enabled by
+    /// `Builder::enable_poll_time_histogram`.
+    pub fn poll_time_histogram_enabled(&self) -> bool {
+        self.handle.config.poll_time_histogram.is_some()
+    }
+
+    /// Number of buckets of the poll time histogram, `0` if disabled.
+    pub fn poll_time_histogram_num_buckets(&self) -> usize {
+        self.handle
+            .config
+            .poll_time_histogram
+            .map_or(0, |config| config.buckets)
+    }
+
+    /// Range of poll times counted by the bucket, the last one is
+    /// unbounded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histogram is disabled or `bucket` is out of range.
+    pub fn poll_time_histogram_bucket_range(&self, bucket: usize) -> Range<Duration> {
+        self.handle
+            .config
+            .poll_time_histogram
+            .expect("poll time histogram is not enabled")
+            .bucket_range(bucket)
+    }
+
+    /// Number of polls of the worker whose duration falls into the bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histogram is disabled, or if `worker` or `bucket` is
+    /// out of range.
+    pub fn worker_poll_time_histogram_bucket_count(&self, worker: usize, bucket: usize) -> u64 {
+        self.worker(worker)
+            .poll_time
+            .as_ref()
+            .expect("poll time histogram is not enabled")
+            .counts[bucket]
+            .load(Ordering::Relaxed)
+    }
+
+    fn worker(&self, worker: usize) -> &WorkerMetrics {
+        &self.handle.shared.workers[worker]
+    }
+}
+
+/// Counters of a worker.
+pub(crate) struct WorkerMetrics {
+    pub(crate) poll_count: AtomicU64,
+    pub(crate) steal_count: AtomicU64,
+    pub(crate) park_count: AtomicU64,
+    /// In nanoseconds.
+    pub(crate) busy_duration: AtomicU64,
+    pub(crate) poll_time: Option<Histogram>,
+}
+
+impl WorkerMetrics {
+    pub(crate) fn new(config: Option<HistogramConfig>) -> Self {
+        Self {
+            poll_count: AtomicU64::new(0),
+            steal_count: AtomicU64::new(0),
+            park_count: AtomicU64::new(0),
+            busy_duration: AtomicU64::new(0),
+            poll_time: config.map(Histogram::new),
+        }
+    }
+
+    /// Count a poll of a task which took `elapsed`.
+    pub(crate) fn record_poll(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.poll_count.fetch_add(1, Ordering::Relaxed);
+        self.busy_duration.fetch_add(nanos, Ordering::Relaxed);
+        if let Some(histogram) = &self.poll_time {
+            histogram.record(nanos);
+        }
+    }
+}
+
+/// Buckets of the poll time histogram, each one twice as wide as the
+/// previous one, starting at `resolution`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HistogramConfig {
+    pub(crate) resolution: Duration,
+    pub(crate) buckets: usize,
+}
+
+impl Default for HistogramConfig {
+    fn default() -> Self {
+        Self {
+            resolution: Duration::from_micros(100),
+            buckets: 10,
+        }
+    }
+}
+
+impl HistogramConfig {
+    fn bucket_range(&self, bucket: usize) -> Range<Duration> {
+        assert!(bucket < self.buckets, "bucket out of range");
+        let start = match bucket {
+            0 => Duration::ZERO,
+            _ => self.resolution * (1 << (bucket - 1)),
+        };
+        let end = if bucket + 1 == self.buckets {
+            Duration::MAX
+        } else {
+            self.resolution * (1 << bucket)
+        };
+        start..end
+    }
+}
+
+pub(crate) struct Histogram {
+    /// In nanoseconds.
+    resolution: u64,
+    counts: Box<[AtomicU64]>,
+}
+
+impl Histogram {
+    fn new(config: HistogramConfig) -> Self {
+        Self {
+            resolution: (config.resolution.as_nanos() as u64).max(1),
+            counts: (0..config.buckets).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn record(&self, nanos: u64) {
+        let bucket = match nanos / self.resolution {
+            0 => 0,
+            n => (u64::BITS - n.leading_zeros()) as usize,
+        };
+        self.counts[bucket.min(self.counts.len() - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+}
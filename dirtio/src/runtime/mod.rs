@@ -1,6 +1,40 @@
+pub mod handle;
+pub use handle::{EnterGuard, Handle, TryCurrentError};
+
 pub mod runtime;
 pub use runtime::{Builder, Runtime};
 
+pub(crate) mod config;
+pub use config::UnhandledPanic;
+
+#[cfg(feature = "test-util")]
+pub use crate::io::fault::IoFaults;
+
+#[cfg(unix)]
+pub(crate) mod console;
+
 pub(crate) mod context;
+pub(crate) mod coop;
+
+pub mod dump;
+pub use dump::{Dump, TaskDump, TaskState};
+
+pub mod metrics;
+pub use metrics::RuntimeMetrics;
+
 pub(crate) mod park;
+
+pub(crate) mod rng;
+
 pub(crate) mod scheduler;
+pub(crate) mod stuck;
+
+#[cfg(all(test, loom))]
+mod tests;
+
+pub(crate) mod time;
+
+pub(crate) mod trace;
+pub use trace::Trace;
+
+pub(crate) mod watchdog;
@@ -1,5 +1,6 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
+use crate::loom::sync::atomic::{AtomicUsize, Ordering};
+use crate::loom::sync::{Arc, Condvar, Mutex};
+
 use std::task::{Wake, Waker};
 
 const EMPTY: usize = 0;
@@ -49,13 +50,19 @@ impl UnparkThread {
         self.inner.unpark();
     }
 
+    /// Returns `true` if both unpark the same thread.
+    pub(crate) fn ptr_eq(&self, other: &UnparkThread) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub(crate) fn into_waker(self) -> Waker {
-        Waker::from(Arc::new(self))
+        // The waker takes the `Arc` of std, even under loom.
+        Waker::from(std::sync::Arc::new(self))
     }
 }
 
 impl Wake for UnparkThread {
-    fn wake(self: Arc<Self>) {
+    fn wake(self: std::sync::Arc<Self>) {
         self.unpark();
     }
 }
@@ -0,0 +1,73 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+/// SplitMix64, a small and fast generator good enough to pick schedules.
+#[derive(Clone, Copy)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "empty range");
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// Returns `true` with probability `p`.
+    #[cfg(any(feature = "sim", feature = "test-util"))]
+    pub(crate) fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    #[cfg(feature = "sim")]
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+thread_local! {
+    /// RNG of current thread, seeded at random on first use.
+    static THREAD_RNG: Cell<Option<Rng>> = const { Cell::new(None) };
+}
+
+/// Returns a number in `0..n` from the RNG of current thread.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+pub(crate) fn thread_below(n: usize) -> usize {
+    THREAD_RNG.with(|cell| {
+        let mut rng = cell
+            .get()
+            .unwrap_or_else(|| Rng::new(RandomState::new().hash_one(0u8)));
+        let n = rng.below(n);
+        cell.set(Some(rng));
+        n
+    })
+}
+
+/// Seed the RNG of current thread, so that a simulation replays.
+#[cfg(feature = "sim")]
+pub(crate) fn seed_thread(seed: u64) {
+    THREAD_RNG.with(|cell| cell.set(Some(Rng::new(seed))));
+}
@@ -1,54 +1,192 @@
-use super::park::ParkThread;
-use super::scheduler::handle::Handle;
+use super::config::{Callback, Config, UnhandledPanic};
+#[cfg(unix)]
+use super::console;
+use super::context;
+use super::handle::{EnterGuard, Handle};
+use super::metrics::{HistogramConfig, RuntimeMetrics};
 use super::scheduler::worker::Worker;
+use super::scheduler::{self, Flavor};
+use super::stuck;
+use super::watchdog;
+#[cfg(feature = "test-util")]
+use super::IoFaults;
 
 use crate::io::driver::Driver;
+use crate::task::TaskMeta;
 
 use std::io;
-use std::task::{Context, Poll};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use futures::{pin_mut, Future};
+use futures::Future;
 
 pub struct Runtime {
     handle: Handle,
+    scheduler: Scheduler,
+}
+
+enum Scheduler {
+    /// Tasks are run by the thread calling `block_on` or `turn`.
+    CurrentThread(Worker),
+    /// Tasks are run by the worker threads.
+    MultiThread,
 }
 
 impl Runtime {
+    /// Returns a handle to the runtime.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Enter the context of the runtime, see `Handle::enter`.
+    pub fn enter(&self) -> EnterGuard<'_> {
+        self.handle.enter()
+    }
+
+    /// Returns the metrics of the runtime.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        self.handle.metrics()
+    }
+
+    /// Run a future to completion on current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a runtime.
     pub fn block_on<F>(&self, fut: F) -> F::Output
     where
         F: Future,
     {
-        self.handle.enter();
+        self.handle.inner.check_shutdown();
+        match &self.scheduler {
+            Scheduler::CurrentThread(worker) => {
+                let _runtime = context::enter_runtime();
+                let _guard = self.handle.inner.enter();
+                worker.block_on(fut)
+            }
+            Scheduler::MultiThread => self.handle.block_on(fut),
+        }
+    }
 
-        let park_thread = ParkThread::new();
-        let unpark_thread = park_thread.unpark();
+    /// Run the tasks which are ready and dispatch the IO events, blocks at
+    /// most `max_time` if there is nothing to do. Returns `true` if any
+    /// task was polled.
+    ///
+    /// This allows a foreign event loop to drive the runtime, by calling
+    /// `turn` whenever the fd of the runtime becomes readable, and by the
+    /// `next_timer_deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime is not a current thread runtime, if called
+    /// from within a runtime, or if the runtime has shut down.
+    pub fn turn(&self, max_time: Duration) -> bool {
+        let Scheduler::CurrentThread(worker) = &self.scheduler else {
+            panic!("`turn` is only supported by the current thread runtime");
+        };
+        self.handle.inner.check_shutdown();
 
-        let waker = unpark_thread.into_waker();
-        let mut cx = Context::from_waker(&waker);
+        let _runtime = context::enter_runtime();
+        let _guard = self.handle.inner.enter();
+        worker.turn(Some(max_time), || true)
+    }
 
-        pin_mut!(fut);
+    /// Returns when the next timer expires by the system clock, `None` if
+    /// there is no timer. The fd of the runtime doesn't become readable for
+    /// the timers, so a foreign event loop must call `turn` by then.
+    ///
+    /// Returns the current time if the time is paused, `turn` advances it.
+    pub fn next_timer_deadline(&self) -> Option<Instant> {
+        let timeout = self.handle.inner.timers.timeout(None)?;
+        Some(Instant::now() + timeout)
+    }
 
-        loop {
-            match fut.as_mut().poll(&mut cx) {
-                Poll::Ready(r) => break r,
-                Poll::Pending => {
-                    park_thread.park();
-                }
-            }
-        }
+    /// Run the tasks and dispatch the IO events until no task can make
+    /// progress, without blocking. Only the expired timers fire, the paused
+    /// time is not advanced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime is not a current thread runtime, if called
+    /// from within a runtime, or if the runtime has shut down.
+    #[cfg(feature = "test-util")]
+    pub fn run_until_stalled(&self) {
+        let Scheduler::CurrentThread(worker) = &self.scheduler else {
+            panic!("`run_until_stalled` is only supported by the current thread runtime");
+        };
+        self.handle.inner.check_shutdown();
+
+        let _runtime = context::enter_runtime();
+        let _guard = self.handle.inner.enter();
+        while worker.turn(Some(Duration::ZERO), || false) {}
     }
 }
 
+/// Shut down the runtime, the worker threads stop after their current
+/// task, and the tasks not completed are dropped. Their `JoinHandle`s
+/// return a cancelled `JoinError`.
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // The tasks may use the runtime while dropped.
+        let _guard = self.handle.inner.enter();
+        self.handle.inner.shutdown();
+    }
+}
+
+/// The fd of the IO driver, it becomes readable when there are IO events
+/// or tasks to run, but not when a timer expires, see
+/// `Runtime::next_timer_deadline`. The fd of the first driver if sharded.
+#[cfg(unix)]
+impl AsRawFd for Runtime {
+    fn as_raw_fd(&self) -> RawFd {
+        self.handle.inner.drivers[0].as_raw_fd()
+    }
+}
+
+/// Name of a worker thread.
+type ThreadNameFn = Arc<dyn Fn() -> String + Send + Sync>;
+
 #[derive(Default)]
 pub struct Builder {
+    flavor: Flavor,
     worker_threads: Option<usize>,
+    sharded_io: bool,
+    thread_name: Option<ThreadNameFn>,
+    thread_stack_size: Option<usize>,
+    after_start: Option<Callback>,
+    before_stop: Option<Callback>,
+    enable_poll_time_histogram: bool,
+    poll_time_histogram: HistogramConfig,
+    #[cfg(unix)]
+    console: Option<PathBuf>,
+    idle_task_threshold: Option<Duration>,
+    config: Config,
 }
 
 impl Builder {
     pub fn new() -> Self {
+        Self::new_multi_thread()
+    }
+
+    /// Tasks are run on the worker threads.
+    pub fn new_multi_thread() -> Self {
         Self {
-            worker_threads: None,
+            flavor: Flavor::MultiThread,
+            ..Default::default()
+        }
+    }
+
+    /// Tasks are run on the thread calling `Runtime::block_on` or
+    /// `Runtime::turn`, no worker threads are spawned.
+    pub fn new_current_thread() -> Self {
+        Self {
+            flavor: Flavor::CurrentThread,
+            ..Default::default()
         }
     }
 
@@ -57,22 +195,366 @@ impl Builder {
         self
     }
 
+    /// Give each worker its own IO driver, instead of sharing one. A source
+    /// is registered on the driver of the worker creating it, and the tasks
+    /// woken by its events stay on that worker unless stolen.
+    ///
+    /// Ignored by the current thread runtime.
+    pub fn sharded_io(&mut self, val: bool) -> &mut Self {
+        self.sharded_io = val;
+        self
+    }
+
+    /// Name of the worker threads, `dirtio-worker` by default.
+    pub fn thread_name(&mut self, val: impl Into<String>) -> &mut Self {
+        let val = val.into();
+        self.thread_name = Some(Arc::new(move || val.clone()));
+        self
+    }
+
+    /// Generate the name of each worker thread.
+    pub fn thread_name_fn<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.thread_name = Some(Arc::new(f));
+        self
+    }
+
+    /// Stack size in bytes of the worker threads.
+    pub fn thread_stack_size(&mut self, val: usize) -> &mut Self {
+        self.thread_stack_size = Some(val);
+        self
+    }
+
+    /// Run on each worker thread after it starts.
+    pub fn on_thread_start<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.after_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Run on each worker thread before it stops.
+    pub fn on_thread_stop<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.before_stop = Some(Arc::new(f));
+        self
+    }
+
+    /// Run before a thread blocks waiting for work, either for a task or
+    /// in the IO driver.
+    pub fn on_thread_park<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.config.before_park = Some(Arc::new(f));
+        self
+    }
+
+    /// Run after a thread wakes up from waiting for work.
+    pub fn on_thread_unpark<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.config.after_unpark = Some(Arc::new(f));
+        self
+    }
+
+    /// Run when a task is spawned, on the spawning thread.
+    pub fn on_task_spawn<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TaskMeta<'_>) + Send + Sync + 'static,
+    {
+        self.config.on_task_spawn = Some(Arc::new(f));
+        self
+    }
+
+    /// Run before a task is polled, on the polling thread.
+    pub fn on_before_task_poll<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TaskMeta<'_>) + Send + Sync + 'static,
+    {
+        self.config.before_task_poll = Some(Arc::new(f));
+        self
+    }
+
+    /// Run after a task is polled, on the polling thread.
+    pub fn on_after_task_poll<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TaskMeta<'_>) + Send + Sync + 'static,
+    {
+        self.config.after_task_poll = Some(Arc::new(f));
+        self
+    }
+
+    /// Run when a task completes, on the polling thread.
+    pub fn on_task_terminate<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TaskMeta<'_>) + Send + Sync + 'static,
+    {
+        self.config.on_task_terminate = Some(Arc::new(f));
+        self
+    }
+
+    /// What to do when a spawned task panics, `UnhandledPanic::Ignore`
+    /// by default. The panic is reported to the `JoinHandle` either way.
+    pub fn unhandled_panic(&mut self, val: UnhandledPanic) -> &mut Self {
+        self.config.unhandled_panic = val;
+        self
+    }
+
+    /// Record the poll times of the tasks in a histogram, see
+    /// `RuntimeMetrics::worker_poll_time_histogram_bucket_count`.
+    pub fn enable_poll_time_histogram(&mut self) -> &mut Self {
+        self.enable_poll_time_histogram = true;
+        self
+    }
+
+    /// Upper bound of the first bucket of the poll time histogram, each
+    /// following bucket is twice as wide. `100us` by default.
+    pub fn poll_time_histogram_resolution(&mut self, val: Duration) -> &mut Self {
+        self.poll_time_histogram.resolution = val;
+        self
+    }
+
+    /// Number of buckets of the poll time histogram, `10` by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is not in `1..=32`.
+    pub fn poll_time_histogram_buckets(&mut self, val: usize) -> &mut Self {
+        assert!((1..=32).contains(&val), "buckets must be in 1..=32");
+        self.poll_time_histogram.buckets = val;
+        self
+    }
+
+    /// Keep the live tasks and the await points of their last poll for
+    /// `Handle::dump`, which is empty otherwise. This costs a lock on each
+    /// spawn and each pending poll.
+    ///
+    /// Enabled by `enable_console` and `warn_on_idle_task` as well.
+    pub fn enable_task_dump(&mut self) -> &mut Self {
+        self.config.task_dump = true;
+        self
+    }
+
+    /// Serve the console on a Unix socket at `path`, for `dirtio-console`
+    /// to inspect the tasks, workers and IO resources of the runtime.
+    ///
+    /// A socket at `path` left by a previous process is removed, `build`
+    /// fails with `AddrInUse` if any other kind of file is there.
+    #[cfg(unix)]
+    pub fn enable_console(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.console = Some(path.into());
+        self
+    }
+
+    /// Warn when a task is polled for longer than `threshold`, which
+    /// usually means it blocks the worker thread.
+    ///
+    /// A watchdog thread reports the task id, name and spawn location while
+    /// the poll is running, and the worker reports the poll duration once
+    /// it returns. The reports are `tracing` warnings with the `tracing`
+    /// feature, they go to stderr otherwise.
+    pub fn warn_on_slow_poll(&mut self, threshold: Duration) -> &mut Self {
+        self.config.slow_poll_threshold = Some(threshold);
+        self
+    }
+
+    /// Report the tasks leaked while pending: all their wakers were dropped,
+    /// so nothing can wake them again. The report names the task, its spawn
+    /// location and the await points it was waiting at.
+    ///
+    /// Wakers dropped on shutdown are not reported. The reports go to
+    /// `tracing` or stderr, as for `warn_on_slow_poll`.
+    pub fn detect_leaked_tasks(&mut self, val: bool) -> &mut Self {
+        self.config.detect_leaked_tasks = val;
+        self
+    }
+
+    /// Warn about the tasks not polled for longer than `threshold`, once per
+    /// idle period. The tasks are checked in a background thread, so the
+    /// report may come up to `threshold / 2` late. The reports go to
+    /// `tracing` or stderr, as for `warn_on_slow_poll`.
+    pub fn warn_on_idle_task(&mut self, threshold: Duration) -> &mut Self {
+        self.idle_task_threshold = Some(threshold);
+        self
+    }
+
+    /// Start the runtime with the time paused, see `time::pause`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is `true` and the runtime is not a current thread
+    /// runtime.
+    #[cfg(feature = "test-util")]
+    pub fn start_paused(&mut self, val: bool) -> &mut Self {
+        assert!(
+            !val || self.flavor == Flavor::CurrentThread,
+            "time can only be paused on the current thread runtime"
+        );
+        self.config.start_paused = val;
+        self
+    }
+
+    /// Inject faults into the IO of the sockets, see `IoFaults`.
+    #[cfg(feature = "test-util")]
+    pub fn inject_io_faults(&mut self, faults: IoFaults) -> &mut Self {
+        self.config.io_faults = Some(faults);
+        self
+    }
+
     pub fn build(&mut self) -> io::Result<Runtime> {
-        let (driver, driver_handle) = Driver::new()?;
+        self.config.poll_time_histogram =
+            Some(self.poll_time_histogram).filter(|_| self.enable_poll_time_histogram);
+        #[cfg(unix)]
+        {
+            self.config.console = self.console.is_some();
+        }
+        // Both read the task dumps.
+        if self.config.console || self.idle_task_threshold.is_some() {
+            self.config.task_dump = true;
+        }
+        if self.flavor == Flavor::CurrentThread {
+            let (mut workers, handle) =
+                Worker::create(1, vec![Driver::new()?], self.flavor, self.config.clone());
+            let worker = workers.pop().unwrap();
+            self.start_background(&handle)?;
+            return Ok(Runtime {
+                handle: Handle { inner: handle },
+                scheduler: Scheduler::CurrentThread(worker),
+            });
+        }
+
         let worker_threads = self.worker_threads.unwrap_or_else(|| {
             thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
         });
 
-        let (mut workers, handle) = Worker::create(worker_threads, driver, driver_handle);
+        let drivers = if self.sharded_io {
+            (0..worker_threads)
+                .map(|_| Driver::new())
+                .collect::<io::Result<_>>()?
+        } else {
+            vec![Driver::new()?]
+        };
 
+        let (mut workers, handle) =
+            Worker::create(worker_threads, drivers, self.flavor, self.config.clone());
+
+        self.start_background(&handle)?;
+
+        let spawner = Arc::new(Spawner {
+            thread_name: self.thread_name.clone(),
+            thread_stack_size: self.thread_stack_size,
+            after_start: self.after_start.clone(),
+            before_stop: self.before_stop.clone(),
+        });
         for worker in workers.drain(..) {
-            let handle = handle.clone();
-            let _ = thread::spawn(move || {
-                handle.enter();
-                worker.run();
-            });
+            if let Err(e) = spawner.spawn(worker) {
+                // Stop the workers already started.
+                handle.shutdown();
+                return Err(e);
+            }
+        }
+
+        Ok(Runtime {
+            handle: Handle { inner: handle },
+            scheduler: Scheduler::MultiThread,
+        })
+    }
+
+    /// Start the console, watchdog and idle task threads if enabled.
+    fn start_background(&self, handle: &scheduler::handle::Handle) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(path) = &self.console {
+            console::spawn(path.clone(), handle.downgrade())?;
+        }
+        if let Some(threshold) = self.config.slow_poll_threshold {
+            watchdog::spawn(threshold, handle.downgrade())?;
+        }
+        if let Some(threshold) = self.idle_task_threshold {
+            stuck::spawn(threshold, handle.downgrade())?;
+        }
+        Ok(())
+    }
+}
+
+/// Spawns the worker threads, and the replacement of a dead one.
+struct Spawner {
+    thread_name: Option<ThreadNameFn>,
+    thread_stack_size: Option<usize>,
+    after_start: Option<Callback>,
+    before_stop: Option<Callback>,
+}
+
+impl Spawner {
+    fn spawn(self: &Arc<Self>, worker: Worker) -> io::Result<()> {
+        let name = self
+            .thread_name
+            .as_ref()
+            .map_or_else(|| "dirtio-worker".to_string(), |f| f());
+        let mut builder = thread::Builder::new().name(name);
+        if let Some(size) = self.thread_stack_size {
+            builder = builder.stack_size(size);
         }
 
-        Ok(Runtime { handle })
+        let spawner = self.clone();
+        builder.spawn(move || {
+            if let Some(f) = &spawner.after_start {
+                f();
+            }
+            let _stop = spawner.before_stop.clone().map(OnDrop);
+
+            let _runtime = context::enter_runtime();
+            let _guard = worker.handle().enter();
+            let supervisor = Supervisor { spawner, worker };
+            supervisor.worker.run();
+        })?;
+        Ok(())
+    }
+}
+
+/// Respawns the worker if its thread dies of a panic, the panics of the
+/// tasks are caught, so this is a bug of the runtime or a panicking hook.
+struct Supervisor {
+    spawner: Arc<Spawner>,
+    worker: Worker,
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        if !thread::panicking() || self.worker.handle().is_shutdown() {
+            return;
+        }
+
+        let index = self.worker.index();
+        match self.spawner.spawn(self.worker.replacement()) {
+            Ok(()) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(worker = index, "worker panicked, respawned it");
+            }
+            Err(e) => {
+                // The runtime runs on with one worker less.
+                eprintln!(
+                    "dirtio: worker {} panicked, failed to respawn it: {}",
+                    index, e
+                );
+                #[cfg(feature = "tracing")]
+                tracing::error!(worker = index, error = %e, "worker panicked, failed to respawn it");
+            }
+        }
+    }
+}
+
+/// Run the hook when dropped, even when the thread is unwinding.
+struct OnDrop(Callback);
+
+impl Drop for OnDrop {
+    fn drop(&mut self) {
+        (self.0)();
     }
 }
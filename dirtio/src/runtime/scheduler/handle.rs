@@ -1,11 +1,22 @@
-use super::join_handle::JoinHandle;
+use super::join_handle::{JoinError, JoinHandle};
+use super::task::{Header, Panicked};
 use super::worker::Shared;
-use super::Task;
+use super::{Flavor, Task};
 
 use crate::io::driver;
+use crate::io::resource::Resources;
+use crate::runtime::config::{Config, UnhandledPanic};
 use crate::runtime::context;
+use crate::runtime::dump::{Dump, TaskState};
+use crate::runtime::park::UnparkThread;
+use crate::runtime::time::{TimerKey, Timers};
+use crate::task::Id;
 
-use std::sync::Arc;
+use std::panic::{AssertUnwindSafe, Location};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::task::Waker;
+use std::time::Instant;
 
 use futures::{Future, FutureExt};
 
@@ -13,18 +24,33 @@ use futures::{Future, FutureExt};
 pub(crate) struct Handle(pub(super) Arc<HandleInner>);
 
 pub(crate) struct HandleInner {
-    pub(super) shared: Shared,
-    pub(crate) driver: driver::Handle,
+    pub(crate) shared: Shared,
+    /// IO drivers, one for each worker if sharded.
+    pub(crate) drivers: Vec<driver::Handle>,
+    pub(crate) flavor: Flavor,
+    pub(crate) config: Config,
+    /// Next driver to register sources from off the workers.
+    pub(super) next_driver: AtomicUsize,
+    /// The registered IO sources, `None` unless the console is enabled.
+    pub(crate) resources: Option<Resources>,
+    pub(crate) timers: Timers,
+    /// Faults injected into the IO, `None` if disabled.
+    #[cfg(feature = "test-util")]
+    pub(crate) faults: Option<crate::io::fault::Faults>,
 }
 
 impl Handle {
-    pub(crate) fn enter(&self) {
-        context::set_current(self.clone());
+    pub(crate) fn enter(&self) -> context::SetCurrentGuard {
+        context::set_current(self.clone())
     }
 
     pub(crate) fn current() -> Self {
         context::current()
     }
+
+    pub(crate) fn downgrade(&self) -> Weak<HandleInner> {
+        Arc::downgrade(&self.0)
+    }
 }
 
 impl std::ops::Deref for Handle {
@@ -36,22 +62,212 @@ impl std::ops::Deref for Handle {
 }
 
 impl HandleInner {
+    #[track_caller]
     pub(crate) fn spawn<F>(&self, fut: F) -> JoinHandle<F::Output>
     where
         F: Future + Send + 'static,
         F::Output: Send,
     {
-        let (sender, handle) = JoinHandle::new();
-        let task = Box::pin(fut.map(|output| sender.send(output).unwrap_or_default()));
+        self.spawn_named(fut, None)
+    }
+
+    #[track_caller]
+    pub(crate) fn spawn_named<F>(&self, fut: F, name: Option<String>) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let id = Id::next();
+        let (sender, handle) = JoinHandle::new(id);
+        // Catch the panic so that it's reported to the `JoinHandle`
+        // instead of killing the worker.
+        let fut = AssertUnwindSafe(fut)
+            .catch_unwind()
+            .map(move |res| match res {
+                Ok(output) => {
+                    let _ = sender.send(Ok(output));
+                    Ok(())
+                }
+                Err(payload) => {
+                    let _ = sender.send(Err(JoinError::panic(id, payload)));
+                    Err(Panicked)
+                }
+            });
+        let task = Task::new(fut, id, name, Location::caller(), &self.shared.tasks);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(parent: &task.header.span, "spawn");
+        if let Some(f) = &self.config.on_task_spawn {
+            f(&task.header.meta());
+        }
         self.schedule(task);
         handle
     }
 
     /// Schedule a task and wake up a worker to process it.
     pub(crate) fn schedule(&self, task: Task) {
+        // Nothing runs the task after shutdown, drop it to cancel.
+        if self.is_shutdown() {
+            return;
+        }
+        task.header.set_state(TaskState::Scheduled);
+
+        // Keep the task on current worker if it has a local queue.
+        if let Some(local) = context::worker(self).and_then(|i| self.shared.local.get(i)) {
+            local.push(task);
+            // Let a worker blocking in its driver steal the tasks piling up.
+            if local.len() > 1 {
+                self.unpark_driver();
+            }
+            return;
+        }
+
         self.shared.task.push(task);
-        if let Some(unparker) = self.shared.unparker.pop() {
-            unparker.unpark();
+        if self.shared.idle.unpark_one() {
+            return;
+        }
+        if self.flavor == Flavor::CurrentThread {
+            // A foreign event loop may wait for the fd of the driver.
+            self.drivers[0].unpark();
+        } else {
+            self.unpark_driver();
+        }
+    }
+
+    /// Wake up a worker blocking in its driver, if any.
+    pub(crate) fn unpark_driver(&self) {
+        // Pairs with the fence in `Worker::poll_driver`, the worker either
+        // sees the task or timer, or is woken up.
+        fence(Ordering::SeqCst);
+        let sleepers = &self.shared.sleepers;
+        if let Some(i) = (0..sleepers.len()).find(|&i| {
+            sleepers[i].load(Ordering::SeqCst) && sleepers[i].swap(false, Ordering::SeqCst)
+        }) {
+            self.drivers[i].unpark();
+        }
+    }
+
+    /// Register a timer, wakes up a worker blocking in its driver past the
+    /// deadline.
+    pub(crate) fn register_timer(
+        &self,
+        key: Option<TimerKey>,
+        deadline: Instant,
+        waker: &Waker,
+    ) -> TimerKey {
+        let new = self.timers.register(key, deadline, waker);
+        if self.is_shutdown() {
+            // Missed by `shutdown`, the waker would keep the task alive.
+            self.timers.remove(new);
+        } else if Some(new) != key && self.is_next_timer(deadline) {
+            self.unpark_driver();
+        }
+        new
+    }
+
+    /// Returns `true` if no timer fires before `deadline`.
+    fn is_next_timer(&self, deadline: Instant) -> bool {
+        !matches!(self.timers.next_deadline(), Some(next) if next < deadline)
+    }
+
+    /// Pick the IO driver to register a source, the driver of current
+    /// worker if sharded.
+    pub(crate) fn pick_driver(&self) -> usize {
+        if self.drivers.len() == 1 {
+            return 0;
+        }
+
+        context::worker(self).unwrap_or_else(|| {
+            self.next_driver.fetch_add(1, Ordering::Relaxed) % self.drivers.len()
+        })
+    }
+
+    pub(crate) fn dump(&self) -> Dump {
+        Dump {
+            tasks: self.shared.tasks.dump(),
+        }
+    }
+
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.shared.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Shut down the runtime, the workers stop after their current task,
+    /// and the other tasks are dropped, which cancels them.
+    pub(crate) fn shutdown(&self) {
+        if self.shared.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        self.shared.idle.unpark_all();
+        for driver in &self.drivers {
+            driver.unpark();
+        }
+        for blocker in self.shared.blockers.lock().unwrap().iter() {
+            blocker.unpark();
+        }
+
+        while self.shared.task.pop().is_some() {}
+        for local in &self.shared.local {
+            while local.pop().is_some() {}
+        }
+
+        // An idle task is only held by its wakers, which the IO sources
+        // and the timers keep. It holds the runtime in turn through its
+        // sources, so drop the wakers to drop the task.
+        for driver in &self.drivers {
+            driver.shutdown();
+        }
+        drop(self.timers.clear());
+    }
+
+    /// Panics if the runtime has shut down.
+    pub(crate) fn check_shutdown(&self) {
+        if !self.is_shutdown() {
+            return;
+        }
+        if self.shared.panicked.load(Ordering::SeqCst) {
+            panic!("a spawned task panicked and the runtime is configured to shut down on unhandled panic");
+        }
+        panic!("the runtime has been shut down");
+    }
+
+    /// Wake up the thread blocking in `Handle::block_on` on shutdown,
+    /// until the guard is dropped.
+    pub(crate) fn add_blocker(&self, unparker: UnparkThread) -> BlockerGuard<'_> {
+        self.shared.blockers.lock().unwrap().push(unparker.clone());
+        BlockerGuard {
+            handle: self,
+            unparker,
+        }
+    }
+
+    /// Apply the `UnhandledPanic` policy after a task panicked.
+    pub(super) fn unhandled_panic(&self, header: &Header) {
+        match self.config.unhandled_panic {
+            UnhandledPanic::Ignore => {}
+            UnhandledPanic::ShutdownRuntime => {
+                eprintln!("dirtio: {} panicked, shutting down the runtime", header);
+                self.shared.panicked.store(true, Ordering::SeqCst);
+                self.shutdown();
+            }
+            UnhandledPanic::Abort => {
+                eprintln!("dirtio: {} panicked, aborting", header);
+                std::process::abort();
+            }
+        }
+    }
+}
+
+pub(crate) struct BlockerGuard<'a> {
+    handle: &'a HandleInner,
+    unparker: UnparkThread,
+}
+
+impl Drop for BlockerGuard<'_> {
+    fn drop(&mut self) {
+        let mut blockers = self.handle.shared.blockers.lock().unwrap();
+        if let Some(i) = blockers.iter().position(|b| b.ptr_eq(&self.unparker)) {
+            blockers.swap_remove(i);
         }
     }
 }
@@ -0,0 +1,64 @@
+use crate::loom::sync::atomic::{fence, AtomicBool, Ordering};
+use crate::loom::sync::Arc;
+use crate::loom::SegQueue;
+use crate::runtime::park::{ParkThread, UnparkThread};
+
+/// The workers parked waiting for tasks.
+pub(crate) struct Idle {
+    sleepers: SegQueue<Sleeper>,
+}
+
+/// A worker registered as idle.
+struct Sleeper {
+    unparker: UnparkThread,
+    /// Cleared by `unpark_one` taking the worker, or by the worker once
+    /// done waiting. The entry of a worker which found work without
+    /// parking stays in the queue, `unpark_one` skips it.
+    waiting: Arc<AtomicBool>,
+}
+
+impl Idle {
+    pub(crate) fn new() -> Self {
+        Self {
+            sleepers: SegQueue::new(),
+        }
+    }
+
+    /// Park the worker until a task is scheduled, returns right away if
+    /// `ready` tells there is work once the worker is registered as idle.
+    pub(crate) fn park(&self, parker: &ParkThread, ready: impl FnOnce() -> bool) {
+        let waiting = Arc::new(AtomicBool::new(true));
+        self.sleepers.push(Sleeper {
+            unparker: parker.unpark(),
+            waiting: waiting.clone(),
+        });
+        // A task scheduled before the worker was registered would be missed,
+        // pairs with the fence in `unpark_one`.
+        fence(Ordering::SeqCst);
+        if !ready() {
+            parker.park();
+        }
+        // Taken by `unpark_one` meanwhile, the swap then synchronizes with
+        // it so that the worker sees the task scheduled.
+        waiting.swap(false, Ordering::SeqCst);
+    }
+
+    /// Unpark an idle worker after a task is scheduled, returns `false` if
+    /// there is none.
+    pub(crate) fn unpark_one(&self) -> bool {
+        fence(Ordering::SeqCst);
+        while let Some(sleeper) = self.sleepers.pop() {
+            if sleeper.waiting.swap(false, Ordering::SeqCst) {
+                sleeper.unparker.unpark();
+                return true;
+            }
+        }
+        false
+    }
+
+    pub(crate) fn unpark_all(&self) {
+        while let Some(sleeper) = self.sleepers.pop() {
+            sleeper.unparker.unpark();
+        }
+    }
+}
@@ -1,22 +1,138 @@
+use crate::runtime::trace;
+use crate::task::Id;
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures::{channel::oneshot, Future, FutureExt};
 
 /// Poll the output of a spawned future
-pub struct JoinHandle<R>(oneshot::Receiver<R>);
+pub struct JoinHandle<R> {
+    id: Id,
+    receiver: oneshot::Receiver<Result<R, JoinError>>,
+}
 
 impl<R> JoinHandle<R> {
-    pub fn new() -> (oneshot::Sender<R>, Self) {
+    pub(crate) fn new(id: Id) -> (oneshot::Sender<Result<R, JoinError>>, Self) {
         let (sender, receiver) = oneshot::channel();
-        (sender, Self(receiver))
+        (sender, Self { id, receiver })
+    }
+
+    /// Returns the id of the task.
+    pub fn id(&self) -> Id {
+        self.id
     }
 }
 
 impl<R> Future for JoinHandle<R> {
-    type Output = R;
+    type Output = Result<R, JoinError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.poll_unpin(cx).map(Result::unwrap)
+        let id = self.id;
+        let res = self.receiver.poll_unpin(cx);
+        if res.is_pending() {
+            trace::leaf("JoinHandle");
+        }
+        // The task is dropped without completion if the runtime shut down.
+        res.map(|res| res.unwrap_or_else(|_| Err(JoinError::cancelled(id))))
+    }
+}
+
+/// Error returned by a `JoinHandle` when the task failed to complete.
+pub struct JoinError {
+    id: Id,
+    repr: Repr,
+}
+
+enum Repr {
+    Cancelled,
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+impl JoinError {
+    pub(crate) fn cancelled(id: Id) -> Self {
+        Self {
+            id,
+            repr: Repr::Cancelled,
+        }
+    }
+
+    pub(crate) fn panic(id: Id, payload: Box<dyn Any + Send + 'static>) -> Self {
+        Self {
+            id,
+            repr: Repr::Panic(payload),
+        }
+    }
+
+    /// Returns the id of the task.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Returns `true` if the task was dropped before completion, when the
+    /// runtime shut down.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.repr, Repr::Cancelled)
+    }
+
+    /// Returns `true` if the task panicked.
+    pub fn is_panic(&self) -> bool {
+        matches!(self.repr, Repr::Panic(_))
+    }
+
+    /// Returns the payload of the panic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the task did not panic.
+    pub fn into_panic(self) -> Box<dyn Any + Send + 'static> {
+        self.try_into_panic()
+            .expect("`JoinError` reason is not a panic")
+    }
+
+    /// Returns the payload of the panic, or the error itself if the task
+    /// did not panic.
+    pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, Self> {
+        match self.repr {
+            Repr::Panic(payload) => Ok(payload),
+            Repr::Cancelled => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            Repr::Cancelled => write!(f, "task {} was cancelled", self.id),
+            Repr::Panic(payload) => match panic_message(payload.as_ref()) {
+                Some(msg) => write!(f, "task {} panicked with message {:?}", self.id, msg),
+                None => write!(f, "task {} panicked", self.id),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            Repr::Cancelled => write!(f, "JoinError::Cancelled({})", self.id),
+            Repr::Panic(payload) => match panic_message(payload.as_ref()) {
+                Some(msg) => write!(f, "JoinError::Panic({}, {:?})", self.id, msg),
+                None => write!(f, "JoinError::Panic({}, ...)", self.id),
+            },
+        }
     }
 }
+
+impl Error for JoinError {}
+
+/// The message of a panic payload, if it is a string.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<&'static str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
@@ -1,18 +1,27 @@
 pub(crate) mod handle;
+pub(crate) mod idle;
 pub(crate) mod join_handle;
-mod task_waker;
+pub(crate) mod task;
+pub(crate) mod task_waker;
 pub(crate) mod worker;
 
 use handle::Handle;
 use join_handle::JoinHandle;
-
-use std::pin::Pin;
+pub(crate) use task::Task;
 
 use futures::Future;
 
-/// A top level future.
-pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+/// Where the tasks get run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Flavor {
+    /// On the thread driving the runtime.
+    CurrentThread,
+    /// On the worker threads.
+    #[default]
+    MultiThread,
+}
 
+#[track_caller]
 pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
 where
     F: Future + Send + 'static,
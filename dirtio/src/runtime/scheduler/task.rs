@@ -0,0 +1,196 @@
+use crate::runtime::dump::{TaskDump, TaskState};
+use crate::runtime::trace::Trace;
+use crate::task::{Id, TaskMeta};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Future;
+
+/// A top level future.
+pub(crate) struct Task {
+    pub(crate) header: Arc<Header>,
+    future: Pin<Box<dyn Future<Output = Result<(), Panicked>> + Send + 'static>>,
+    _registered: Registered,
+}
+
+/// The task panicked, the payload is passed to its `JoinHandle`.
+pub(crate) struct Panicked;
+
+/// Metadata of a task.
+pub(crate) struct Header {
+    pub(crate) id: Id,
+    pub(crate) name: Option<String>,
+    pub(crate) location: &'static Location<'static>,
+    state: AtomicU8,
+    /// Await points at the end of the last poll, only set when traces are
+    /// captured.
+    trace: Mutex<Trace>,
+    pub(crate) stats: TaskStats,
+    /// Entered when the task is polled.
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
+}
+
+/// Counters of a task.
+#[derive(Default)]
+pub(crate) struct TaskStats {
+    polls: AtomicU64,
+    pub(crate) wakes: AtomicU64,
+    /// Time spent in polls, in nanoseconds.
+    busy: AtomicU64,
+}
+
+/// The live tasks of a runtime.
+pub(crate) struct Registry {
+    alive: AtomicUsize,
+    /// The tasks by id, `None` unless task dumps are enabled.
+    tasks: Option<Mutex<HashMap<Id, Arc<Header>>>>,
+}
+
+/// Keeps a task in the registry until it's dropped.
+struct Registered {
+    registry: Arc<Registry>,
+    id: Id,
+}
+
+impl Task {
+    pub(crate) fn new<F>(
+        fut: F,
+        id: Id,
+        name: Option<String>,
+        location: &'static Location<'static>,
+        registry: &Arc<Registry>,
+    ) -> Self
+    where
+        F: Future<Output = Result<(), Panicked>> + Send + 'static,
+    {
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!(
+            "task",
+            task.id = %id,
+            task.name = name.as_deref(),
+            spawned_at = %location,
+        );
+        let header = Arc::new(Header {
+            id,
+            name,
+            location,
+            state: AtomicU8::new(TaskState::Scheduled as u8),
+            trace: Mutex::default(),
+            stats: TaskStats::default(),
+            #[cfg(feature = "tracing")]
+            span,
+        });
+        registry.alive.fetch_add(1, Ordering::Relaxed);
+        if let Some(tasks) = &registry.tasks {
+            tasks.lock().unwrap().insert(id, header.clone());
+        }
+
+        Self {
+            header,
+            future: Box::pin(fut),
+            _registered: Registered {
+                registry: registry.clone(),
+                id,
+            },
+        }
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Panicked>> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+impl Header {
+    pub(crate) fn meta(&self) -> TaskMeta<'_> {
+        TaskMeta {
+            id: self.id,
+            name: self.name.as_deref(),
+            location: self.location,
+        }
+    }
+
+    pub(crate) fn set_state(&self, state: TaskState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_trace(&self, trace: Trace) {
+        *self.trace.lock().unwrap() = trace;
+    }
+
+    pub(crate) fn trace(&self) -> Trace {
+        self.trace.lock().unwrap().clone()
+    }
+
+    fn dump(&self) -> TaskDump {
+        TaskDump {
+            id: self.id,
+            name: self.name.clone(),
+            location: self.location,
+            state: TaskState::from_u8(self.state.load(Ordering::Relaxed)),
+            polls: self.stats.polls.load(Ordering::Relaxed),
+            wakes: self.stats.wakes.load(Ordering::Relaxed),
+            busy: Duration::from_nanos(self.stats.busy.load(Ordering::Relaxed)),
+            trace: self.trace(),
+        }
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "task {}", self.id)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({})", name)?;
+        }
+        write!(f, " spawned at {}", self.location)
+    }
+}
+
+impl TaskStats {
+    pub(crate) fn record_poll(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.busy.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Registry {
+    pub(crate) fn new(dump: bool) -> Self {
+        Self {
+            alive: AtomicUsize::new(0),
+            tasks: dump.then(Mutex::default),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.alive.load(Ordering::Relaxed)
+    }
+
+    /// Snapshot the live tasks, ordered by id. Empty unless task dumps
+    /// are enabled.
+    pub(crate) fn dump(&self) -> Vec<TaskDump> {
+        let Some(tasks) = &self.tasks else {
+            return Vec::new();
+        };
+        let headers: Vec<_> = tasks.lock().unwrap().values().cloned().collect();
+        let mut tasks: Vec<_> = headers.iter().map(|header| header.dump()).collect();
+        tasks.sort_by_key(|task| task.id);
+        tasks
+    }
+}
+
+impl Drop for Registered {
+    fn drop(&mut self) {
+        if let Some(tasks) = &self.registry.tasks {
+            tasks.lock().unwrap().remove(&self.id);
+        }
+        self.registry.alive.fetch_sub(1, Ordering::Relaxed);
+    }
+}
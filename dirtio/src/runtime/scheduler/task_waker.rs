@@ -1,32 +1,128 @@
+use super::task::Header;
 use super::{Handle, Task};
 
-use std::sync::{Arc, Mutex};
+use crate::loom::sync::Mutex;
+use crate::runtime::dump::TaskState;
+use crate::runtime::stuck;
+
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, PoisonError};
 use std::task::Wake;
 
 /// Waker for the top level future.
 pub(crate) struct TaskWaker {
     handle: Handle,
-    task: Mutex<Option<Task>>,
+    slot: Slot<Task>,
+    header: Arc<Header>,
 }
 
 impl TaskWaker {
-    pub(crate) fn new(handle: Handle) -> Self {
+    pub(crate) fn new(handle: Handle, header: Arc<Header>) -> Self {
         Self {
             handle,
-            task: Mutex::new(None),
+            slot: Slot::new(),
+            header,
         }
     }
 
+    /// Put the task into the waker after a pending poll, reschedule it
+    /// right away if woken meanwhile.
     pub(crate) fn task(&self, task: Task) {
-        self.task.lock().unwrap().replace(task);
+        task.header.set_state(TaskState::Idle);
+        if let Some(task) = self.slot.put(task) {
+            self.handle.schedule(task);
+        }
     }
 }
 
 impl Wake for TaskWaker {
     fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(parent: &self.header.span, "wake");
+        self.header.stats.wakes.fetch_add(1, Ordering::Relaxed);
+
         // Reschedule the task at wakeup.
-        if let Some(task) = self.task.lock().unwrap().take() {
+        if let Some(task) = self.slot.wake() {
             self.handle.schedule(task);
         }
     }
 }
+
+impl Drop for TaskWaker {
+    fn drop(&mut self) {
+        // The task is dropped with the last waker, it can never complete.
+        // Expected on shutdown, when the sources holding the wakers drop.
+        if self.handle.config.detect_leaked_tasks
+            && !self.handle.is_shutdown()
+            && self.slot.is_idle()
+        {
+            stuck::report_leaked(&self.header);
+        }
+    }
+}
+
+/// Holds a task between a pending poll and its wakeup, so that exactly one
+/// of the end of the poll and the wakeups reschedules it.
+pub(crate) struct Slot<T> {
+    state: Mutex<State<T>>,
+}
+
+enum State<T> {
+    /// The task is being polled.
+    Running,
+    /// Woken while being polled, reschedule it after the poll.
+    Notified,
+    /// Waiting for a wakeup.
+    Idle(T),
+    /// Rescheduled, later wakeups belong to a stale poll.
+    Scheduled,
+}
+
+impl<T> Slot<T> {
+    /// Create the slot of a task being polled.
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(State::Running),
+        }
+    }
+
+    /// Put the task after a pending poll, returns it back to reschedule if
+    /// woken meanwhile.
+    pub(crate) fn put(&self, task: T) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Running => {
+                *state = State::Idle(task);
+                None
+            }
+            State::Notified => {
+                *state = State::Scheduled;
+                Some(task)
+            }
+            State::Idle(_) | State::Scheduled => unreachable!("task put into the waker twice"),
+        }
+    }
+
+    /// Wake the task, returns it to reschedule if it was put.
+    pub(crate) fn wake(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        match std::mem::replace(&mut *state, State::Scheduled) {
+            State::Idle(task) => Some(task),
+            State::Running | State::Notified => {
+                *state = State::Notified;
+                None
+            }
+            State::Scheduled => None,
+        }
+    }
+
+    /// Returns `true` if the task waits for a wakeup.
+    pub(crate) fn is_idle(&self) -> bool {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        matches!(*state, State::Idle(_))
+    }
+}
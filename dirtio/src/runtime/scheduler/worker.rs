@@ -1,45 +1,129 @@
 use super::handle::{Handle, HandleInner};
+use super::idle::Idle;
+use super::task::Registry;
 use super::task_waker::TaskWaker;
-use super::Task;
+use super::{Flavor, Task};
 
 use crate::io::driver::{self, Driver};
+#[cfg(feature = "test-util")]
+use crate::io::fault::Faults;
+use crate::io::resource::Resources;
+use crate::runtime::config::Config;
+use crate::runtime::dump::TaskState;
+use crate::runtime::metrics::WorkerMetrics;
 use crate::runtime::park::{ParkThread, UnparkThread};
+use crate::runtime::time::Timers;
+use crate::runtime::trace::Trace;
+use crate::runtime::watchdog::PollWatch;
+use crate::runtime::{context, coop, trace};
 
-use std::sync::{Arc, Mutex};
-use std::task;
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::task::{self, Wake};
+use std::time::{Duration, Instant};
 
 use crossbeam_queue::SegQueue;
+use futures::{pin_mut, Future};
+
+/// Number of tasks to run before polling the own driver of a worker.
+const EVENT_INTERVAL: u32 = 61;
 
 pub(crate) struct Worker {
+    index: usize,
     handle: Handle,
     driver: Arc<Mutex<Driver>>,
     parker: ParkThread,
 }
 
 /// Share states for the workers.
-#[derive(Default)]
 pub(crate) struct Shared {
     pub(super) task: SegQueue<Task>,
-    pub(super) unparker: SegQueue<UnparkThread>,
+    /// The workers parked waiting for tasks.
+    pub(super) idle: Idle,
+    /// Local queues of the workers if the drivers are sharded.
+    pub(super) local: Vec<SegQueue<Task>>,
+    /// Set while a worker blocks in the driver, one for each driver.
+    pub(super) sleepers: Vec<AtomicBool>,
+    /// Set when the runtime shuts down.
+    pub(super) shutdown: AtomicBool,
+    /// Set if the shutdown is caused by a panicked task.
+    pub(super) panicked: AtomicBool,
+    /// Threads blocking in `Handle::block_on`, woken on shutdown.
+    pub(super) blockers: Mutex<Vec<UnparkThread>>,
+    /// The tasks not yet dropped.
+    pub(super) tasks: Arc<Registry>,
+    pub(crate) workers: Vec<WorkerMetrics>,
+    /// The polls running on the workers, for the watchdog.
+    pub(crate) watches: Vec<PollWatch>,
+}
+
+impl Shared {
+    pub(crate) fn alive_tasks(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub(crate) fn global_queue_depth(&self) -> usize {
+        self.task.len()
+    }
 }
 
 impl Worker {
+    /// Create the workers, they share the driver if only one given,
+    /// otherwise each of them owns one.
     pub(crate) fn create(
         size: usize,
-        driver: Driver,
-        driver_handle: driver::Handle,
+        drivers: Vec<(Driver, driver::Handle)>,
+        flavor: Flavor,
+        config: Config,
     ) -> (Vec<Worker>, Handle) {
-        let driver = Arc::new(Mutex::new(driver));
-        let shared = Shared::default();
+        let sharded = drivers.len() > 1;
+        let (drivers, driver_handles): (Vec<_>, Vec<_>) = drivers
+            .into_iter()
+            .map(|(driver, handle)| (Arc::new(Mutex::new(driver)), handle))
+            .unzip();
+
+        let shared = Shared {
+            task: SegQueue::new(),
+            idle: Idle::new(),
+            local: if sharded {
+                (0..size).map(|_| SegQueue::new()).collect()
+            } else {
+                Vec::new()
+            },
+            sleepers: (0..drivers.len()).map(|_| AtomicBool::new(false)).collect(),
+            shutdown: AtomicBool::new(false),
+            panicked: AtomicBool::new(false),
+            blockers: Mutex::new(Vec::new()),
+            tasks: Arc::new(Registry::new(config.task_dump)),
+            workers: (0..size)
+                .map(|_| WorkerMetrics::new(config.poll_time_histogram))
+                .collect(),
+            watches: (0..size).map(|_| PollWatch::default()).collect(),
+        };
+        // Time can be paused if a single thread runs all the tasks.
+        let pausable =
+            config.start_paused || (cfg!(feature = "test-util") && flavor == Flavor::CurrentThread);
+        let timers = Timers::new(pausable, config.start_paused);
+        #[cfg(feature = "test-util")]
+        let faults = config.io_faults.clone().map(Faults::new);
+        let resources = config.console.then(Resources::default);
         let handle = Handle(Arc::new(HandleInner {
             shared,
-            driver: driver_handle,
+            drivers: driver_handles,
+            flavor,
+            config,
+            next_driver: AtomicUsize::new(0),
+            resources,
+            timers,
+            #[cfg(feature = "test-util")]
+            faults,
         }));
 
         let workers = (0..size)
-            .map(|_| Worker {
+            .map(|index| Worker {
+                index,
                 handle: handle.clone(),
-                driver: driver.clone(),
+                driver: drivers[if sharded { index } else { 0 }].clone(),
                 parker: ParkThread::new(),
             })
             .collect();
@@ -47,10 +131,37 @@ impl Worker {
         (workers, handle)
     }
 
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(crate) fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Create a worker to replace this one, after its thread died.
+    pub(crate) fn replacement(&self) -> Worker {
+        Worker {
+            index: self.index,
+            handle: self.handle.clone(),
+            driver: self.driver.clone(),
+            parker: ParkThread::new(),
+        }
+    }
+
+    /// Run the tasks until the runtime shuts down.
     pub(crate) fn run(&self) {
+        if !self.handle.shared.local.is_empty() {
+            return self.run_sharded();
+        }
+
         loop {
-            let mut task = {
+            let task = {
                 loop {
+                    if self.handle.is_shutdown() {
+                        return;
+                    }
+
                     if let Some(task) = self.handle.shared.task.pop() {
                         break task;
                     }
@@ -59,25 +170,293 @@ impl Worker {
                     //
                     // Poll events if acquire the lock,
                     // otherwise park the thread.
-                    if let Ok(mut driver) = self.driver.try_lock() {
-                        driver.poll_events();
+                    if let Some(mut driver) = self.try_lock_driver() {
+                        self.poll_driver(&mut driver, None);
+                        drop(driver);
+                        self.handle.timers.process();
                     } else {
-                        self.handle.shared.unparker.push(self.parker.unpark());
-                        self.parker.park();
+                        let shared = &self.handle.shared;
+                        self.park(|| {
+                            shared.idle.park(&self.parker, || {
+                                !shared.task.is_empty() || self.handle.is_shutdown()
+                            })
+                        });
                     }
                 }
             };
 
-            // Poll the task if there is one
-            let inner = Arc::new(TaskWaker::new(self.handle.clone()));
-            let waker = task::Waker::from(inner.clone());
-            let mut cx = task::Context::from_waker(&waker);
+            self.run_task(task);
+        }
+    }
+
+    /// Run with an own driver, the tasks woken by it are kept in the
+    /// local queue, idle workers steal from others.
+    fn run_sharded(&self) {
+        context::set_worker(&self.handle, self.index);
+
+        let mut tick: u32 = 0;
+        while !self.handle.is_shutdown() {
+            match self.next_task() {
+                Some(task) => {
+                    self.run_task(task);
+
+                    // Don't starve the sources of this worker when busy.
+                    tick = tick.wrapping_add(1);
+                    if tick.is_multiple_of(EVENT_INTERVAL) {
+                        self.poll_events(Some(Duration::ZERO));
+                    }
+                }
+                None => self.poll_events(None),
+            }
+        }
+    }
+
+    fn next_task(&self) -> Option<Task> {
+        let shared = &self.handle.shared;
+        shared.local[self.index]
+            .pop()
+            .or_else(|| shared.task.pop())
+            .or_else(|| self.steal())
+    }
+
+    /// Steal a task from the local queue of other workers.
+    fn steal(&self) -> Option<Task> {
+        let local = &self.handle.shared.local;
+        let task = (1..local.len())
+            .map(|i| (self.index + i) % local.len())
+            .find_map(|i| local[i].pop());
+        if task.is_some() {
+            self.metrics().steal_count.fetch_add(1, Ordering::Relaxed);
+        }
+        task
+    }
+
+    /// Run the tasks on current thread until the future completes.
+    pub(crate) fn block_on<F>(&self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        let notified = Arc::new(Notified {
+            handle: self.handle.clone(),
+            notified: AtomicBool::new(true),
+        });
+        let waker = task::Waker::from(notified.clone());
+        let mut cx = task::Context::from_waker(&waker);
+
+        pin_mut!(fut);
+
+        loop {
+            if notified.notified.swap(false, Ordering::SeqCst) {
+                if let task::Poll::Ready(r) = coop::budget(|| fut.as_mut().poll(&mut cx)) {
+                    break r;
+                }
+            }
+
+            self.turn(None, || !notified.notified.load(Ordering::SeqCst));
+            self.handle.check_shutdown();
+        }
+    }
+
+    /// Run the tasks ready now and dispatch the IO events once, blocks in
+    /// the driver at most `timeout` if there is no task to run.
+    ///
+    /// If the time is paused and nothing is left to run, it's advanced to
+    /// the next timer. `idle` tells if the caller has nothing to run.
+    ///
+    /// Returns `true` if any task was polled.
+    pub(crate) fn turn(&self, timeout: Option<Duration>, idle: impl Fn() -> bool) -> bool {
+        let mut progress = self.run_ready();
+        self.poll_events(if progress {
+            Some(Duration::ZERO)
+        } else {
+            timeout
+        });
+        progress |= self.run_ready();
+
+        if !progress && idle() && self.handle.timers.auto_advance() {
+            progress |= self.run_ready();
+        }
+        progress
+    }
+
+    /// Dispatch the IO events and fire the expired timers, blocks in the
+    /// driver at most `timeout`, see `poll_driver`.
+    pub(crate) fn poll_events(&self, timeout: Option<Duration>) {
+        let mut driver = self.lock_driver();
+        self.poll_driver(&mut driver, timeout);
+        drop(driver);
 
-            if task.as_mut().poll(&mut cx).is_pending() {
-                // Put the task into the waker, then in the
-                // next wakeup, the task will be rescheduled
+        self.handle.timers.process();
+    }
+
+    /// Poll the driver, blocks at most `timeout` and until the next timer,
+    /// unless there are tasks to run. The blocked worker is woken up by the
+    /// tasks scheduled and the earlier timers registered from other threads.
+    fn poll_driver(&self, driver: &mut Driver, timeout: Option<Duration>) {
+        if timeout == Some(Duration::ZERO) {
+            return driver.poll_events(timeout);
+        }
+
+        let sleeping = &self.handle.shared.sleepers[self.driver_index()];
+        sleeping.store(true, Ordering::SeqCst);
+        // Pairs with the fence in `HandleInner::unpark_driver`.
+        fence(Ordering::SeqCst);
+        let timeout = if self.has_tasks() {
+            Some(Duration::ZERO)
+        } else {
+            self.handle.timers.timeout(timeout)
+        };
+        if timeout == Some(Duration::ZERO) {
+            driver.poll_events(timeout);
+        } else {
+            self.park(|| driver.poll_events(timeout));
+        }
+        sleeping.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if a task waits in the global queue or a local one.
+    fn has_tasks(&self) -> bool {
+        let shared = &self.handle.shared;
+        !shared.task.is_empty() || shared.local.iter().any(|local| !local.is_empty())
+    }
+
+    /// Index of the driver of the worker.
+    fn driver_index(&self) -> usize {
+        if self.handle.shared.local.is_empty() {
+            0
+        } else {
+            self.index
+        }
+    }
+
+    /// Pop a task from the global queue.
+    #[cfg(feature = "sim")]
+    pub(crate) fn pop_task(&self) -> Option<Task> {
+        self.handle.shared.task.pop()
+    }
+
+    /// Run the tasks in the queue, tasks scheduled meanwhile are left
+    /// for the next turn.
+    fn run_ready(&self) -> bool {
+        let n = self.handle.shared.task.len();
+        for _ in 0..n {
+            match self.handle.shared.task.pop() {
+                Some(task) => self.run_task(task),
+                None => break,
+            }
+        }
+        n > 0
+    }
+
+    /// Lock the driver, ignoring the poison left by a worker which died
+    /// while holding it.
+    fn lock_driver(&self) -> MutexGuard<'_, Driver> {
+        self.driver.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_lock_driver(&self) -> Option<MutexGuard<'_, Driver>> {
+        match self.driver.try_lock() {
+            Ok(driver) => Some(driver),
+            Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn metrics(&self) -> &WorkerMetrics {
+        &self.handle.shared.workers[self.index]
+    }
+
+    /// Block waiting for work, between the park hooks.
+    fn park(&self, wait: impl FnOnce()) {
+        self.metrics().park_count.fetch_add(1, Ordering::Relaxed);
+        let config = &self.handle.config;
+        if let Some(f) = &config.before_park {
+            f();
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(worker = self.index, "park");
+        wait();
+        #[cfg(feature = "tracing")]
+        tracing::trace!(worker = self.index, "unpark");
+        if let Some(f) = &config.after_unpark {
+            f();
+        }
+    }
+
+    pub(crate) fn run_task(&self, mut task: Task) {
+        let inner = Arc::new(TaskWaker::new(self.handle.clone(), task.header.clone()));
+        let waker = task::Waker::from(inner.clone());
+        let mut cx = task::Context::from_waker(&waker);
+
+        let config = &self.handle.config;
+        if let Some(f) = &config.before_task_poll {
+            f(&task.header.meta());
+        }
+        task.header.set_state(TaskState::Running);
+        let watch = config
+            .slow_poll_threshold
+            .map(|_| &self.handle.shared.watches[self.index]);
+        if let Some(watch) = watch {
+            watch.enter(&task.header);
+        }
+        let start = Instant::now();
+        let (res, trace) = {
+            #[cfg(feature = "tracing")]
+            let _span = task.header.span.clone().entered();
+            #[cfg(feature = "tracing")]
+            tracing::trace!("poll");
+
+            let _task = context::set_current_task(task.header.id);
+            let mut poll = || coop::budget(|| task.poll(&mut cx));
+            if config.capture_traces() {
+                trace::capture(poll)
+            } else {
+                (poll(), Trace::default())
+            }
+        };
+        let elapsed = start.elapsed();
+        if let Some(watch) = watch {
+            watch.exit(self.index, &trace);
+        }
+        self.metrics().record_poll(elapsed);
+        task.header.stats.record_poll(elapsed);
+        if let Some(f) = &config.after_task_poll {
+            f(&task.header.meta());
+        }
+
+        match res {
+            // Put the task into the waker, then in the
+            // next wakeup, the task will be rescheduled
+            task::Poll::Pending => {
+                if config.capture_traces() {
+                    task.header.set_trace(trace);
+                }
                 inner.task(task);
             }
+            task::Poll::Ready(res) => {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(parent: &task.header.span, panicked = res.is_err(), "complete");
+                if let Some(f) = &config.on_task_terminate {
+                    f(&task.header.meta());
+                }
+                if res.is_err() {
+                    self.handle.unhandled_panic(&task.header);
+                }
+            }
         }
     }
 }
+
+/// Waker for the future passed to `block_on`.
+struct Notified {
+    handle: Handle,
+    notified: AtomicBool,
+}
+
+impl Wake for Notified {
+    fn wake(self: Arc<Self>) {
+        self.notified.store(true, Ordering::SeqCst);
+        // There is only one driver in the current thread runtime.
+        self.handle.drivers[0].unpark();
+    }
+}
@@ -0,0 +1,104 @@
+//! Detection of the tasks which may never complete, set by
+//! `Builder::detect_leaked_tasks` and `Builder::warn_on_idle_task`.
+
+use super::dump::TaskState;
+use super::scheduler::handle::HandleInner;
+use super::scheduler::task::Header;
+use super::watchdog::report;
+
+use crate::task::Id;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Max interval of checking the tasks.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Report a pending task dropped along with its waker, nothing can
+/// poll it again.
+pub(crate) fn report_leaked(header: &Header) {
+    let trace = header.trace();
+    let mut msg = format!(
+        "{} leaked, its wakers were dropped while it was pending",
+        header
+    );
+    if !trace.is_empty() {
+        let _ = write!(msg, ", it was waiting at:\n{}", trace);
+    }
+    report(header.id, format_args!("{}", msg.trim_end()));
+}
+
+/// A task seen idle by the checker.
+struct Idle {
+    /// Polls of the task when first seen idle.
+    polls: u64,
+    since: Instant,
+    reported: bool,
+}
+
+/// Check the idle tasks in a background thread, which exits when the
+/// runtime shuts down.
+pub(crate) fn spawn(threshold: Duration, handle: Weak<HandleInner>) -> io::Result<()> {
+    let interval = (threshold / 2).clamp(Duration::from_millis(1), CHECK_INTERVAL);
+    thread::Builder::new()
+        .name("dirtio-stuck".to_string())
+        .spawn(move || {
+            let mut idle: HashMap<Id, Idle> = HashMap::new();
+            loop {
+                let Some(handle) = handle.upgrade() else {
+                    return;
+                };
+                if handle.is_shutdown() {
+                    return;
+                }
+                check(&handle, threshold, &mut idle);
+                drop(handle);
+                thread::sleep(interval);
+            }
+        })?;
+    Ok(())
+}
+
+/// Report the tasks not polled since `threshold` ago, once per idle
+/// period.
+fn check(handle: &HandleInner, threshold: Duration, idle: &mut HashMap<Id, Idle>) {
+    let now = Instant::now();
+    let mut prev = std::mem::take(idle);
+    for task in handle.dump().tasks() {
+        if task.state() != TaskState::Idle {
+            continue;
+        }
+        // A task polled since the last check started a new idle period.
+        let entry = match prev.remove(&task.id()) {
+            Some(entry) if entry.polls == task.polls() => entry,
+            _ => Idle {
+                polls: task.polls(),
+                since: now,
+                reported: false,
+            },
+        };
+        let entry = idle.entry(task.id()).or_insert(entry);
+
+        if !entry.reported && now - entry.since >= threshold {
+            entry.reported = true;
+            let mut msg = format!("task {}", task.id());
+            if let Some(name) = task.name() {
+                let _ = write!(msg, " ({})", name);
+            }
+            let _ = write!(
+                msg,
+                " spawned at {} has been idle for {:?}",
+                task.spawned_at(),
+                now - entry.since
+            );
+            if !task.trace().is_empty() {
+                let _ = write!(msg, ", it is waiting at:\n{}", task.trace());
+            }
+            report(task.id(), format_args!("{}", msg.trim_end()));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::runtime::park::ParkThread;
+use crate::runtime::scheduler::idle::Idle;
+
+use loom::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+/// Run a worker until it took `n` tasks from `queue`, the queue stands in
+/// for the task queue of the runtime.
+fn worker(idle: &Idle, queue: &AtomicUsize, n: usize) {
+    let parker = ParkThread::new();
+    let mut taken = 0;
+    while taken < n {
+        if queue.load(Ordering::Acquire) > 0 {
+            queue.fetch_sub(1, Ordering::AcqRel);
+            taken += 1;
+            continue;
+        }
+        idle.park(&parker, || queue.load(Ordering::Acquire) > 0);
+    }
+}
+
+fn schedule(idle: &Idle, queue: &AtomicUsize) {
+    queue.fetch_add(1, Ordering::Release);
+    idle.unpark_one();
+}
+
+#[test]
+fn schedule_while_parking() {
+    loom::model(|| {
+        let idle = Arc::new(Idle::new());
+        let queue = Arc::new(AtomicUsize::new(0));
+        let th = thread::spawn({
+            let idle = idle.clone();
+            let queue = queue.clone();
+            move || schedule(&idle, &queue)
+        });
+        worker(&idle, &queue, 1);
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn concurrent_schedules() {
+    // Three threads, bound the preemptions to keep the run short.
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound = Some(2);
+    builder.check(|| {
+        let idle = Arc::new(Idle::new());
+        let queue = Arc::new(AtomicUsize::new(0));
+        let ths: Vec<_> = (0..2)
+            .map(|_| {
+                let idle = idle.clone();
+                let queue = queue.clone();
+                thread::spawn(move || schedule(&idle, &queue))
+            })
+            .collect();
+        worker(&idle, &queue, 2);
+        for th in ths {
+            th.join().unwrap();
+        }
+    });
+}
+
+#[test]
+fn shutdown_while_parking() {
+    loom::model(|| {
+        let idle = Arc::new(Idle::new());
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let th = thread::spawn({
+            let idle = idle.clone();
+            let shutdown = shutdown.clone();
+            move || {
+                shutdown.store(true, Ordering::SeqCst);
+                idle.unpark_all();
+            }
+        });
+        let parker = ParkThread::new();
+        while !shutdown.load(Ordering::SeqCst) {
+            idle.park(&parker, || shutdown.load(Ordering::SeqCst));
+        }
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn found_work_leaves_no_idle_worker() {
+    loom::model(|| {
+        let idle = Idle::new();
+        let parker = ParkThread::new();
+        idle.park(&parker, || true);
+        assert!(!idle.unpark_one());
+    });
+}
+
+#[test]
+fn schedule_after_found_work() {
+    loom::model(|| {
+        let idle = Arc::new(Idle::new());
+        let queue = Arc::new(AtomicUsize::new(0));
+        // Set if the scheduler fell back to waking the driver.
+        let driver = Arc::new(AtomicBool::new(false));
+        let th = thread::spawn({
+            let idle = idle.clone();
+            let queue = queue.clone();
+            let driver = driver.clone();
+            move || {
+                queue.fetch_add(1, Ordering::Release);
+                if !idle.unpark_one() {
+                    driver.store(true, Ordering::SeqCst);
+                }
+            }
+        });
+
+        // Found work, then checks the queue a last time before blocking
+        // in the driver.
+        let parker = ParkThread::new();
+        idle.park(&parker, || true);
+        let seen = queue.load(Ordering::Acquire) > 0;
+        th.join().unwrap();
+        assert!(seen || driver.load(Ordering::SeqCst));
+    });
+}
@@ -0,0 +1,62 @@
+use crate::runtime::park::ParkThread;
+
+use loom::sync::atomic::{AtomicBool, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+#[test]
+fn unpark_before_park() {
+    loom::model(|| {
+        let parker = ParkThread::new();
+        parker.unpark().unpark();
+        parker.park();
+    });
+}
+
+#[test]
+fn park_unpark_race() {
+    loom::model(|| {
+        let parker = ParkThread::new();
+        let unparker = parker.unpark();
+        let th = thread::spawn(move || unparker.unpark());
+        parker.park();
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn unpark_is_not_lost() {
+    loom::model(|| {
+        let parker = ParkThread::new();
+        let unparker = parker.unpark();
+        let done = Arc::new(AtomicBool::new(false));
+        let th = thread::spawn({
+            let done = done.clone();
+            move || {
+                done.store(true, Ordering::Release);
+                unparker.unpark();
+            }
+        });
+        while !done.load(Ordering::Acquire) {
+            parker.park();
+        }
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn concurrent_unparks() {
+    loom::model(|| {
+        let parker = ParkThread::new();
+        let ths: Vec<_> = (0..2)
+            .map(|_| {
+                let unparker = parker.unpark();
+                thread::spawn(move || unparker.unpark())
+            })
+            .collect();
+        parker.park();
+        for th in ths {
+            th.join().unwrap();
+        }
+    });
+}
@@ -0,0 +1,56 @@
+use crate::runtime::scheduler::task_waker::Slot;
+
+use loom::sync::Arc;
+use loom::thread;
+
+#[test]
+fn wake_during_poll() {
+    loom::model(|| {
+        let slot = Arc::new(Slot::new());
+        let th = thread::spawn({
+            let slot = slot.clone();
+            move || slot.wake()
+        });
+        let put = slot.put(1);
+        let woken = th.join().unwrap();
+        // Rescheduled exactly once, by whichever came last.
+        assert_eq!(put.xor(woken), Some(1));
+    });
+}
+
+#[test]
+fn concurrent_wakes() {
+    loom::model(|| {
+        let slot = Arc::new(Slot::new());
+        assert_eq!(slot.put(1), None);
+        assert!(slot.is_idle());
+        let ths: Vec<_> = (0..2)
+            .map(|_| {
+                let slot = slot.clone();
+                thread::spawn(move || slot.wake())
+            })
+            .collect();
+        let woken: Vec<_> = ths
+            .into_iter()
+            .filter_map(|th| th.join().unwrap())
+            .collect();
+        assert_eq!(woken, [1]);
+        assert!(!slot.is_idle());
+    });
+}
+
+#[test]
+fn wakes_racing_with_poll() {
+    loom::model(|| {
+        let slot = Arc::new(Slot::new());
+        let ths: Vec<_> = (0..2)
+            .map(|_| {
+                let slot = slot.clone();
+                thread::spawn(move || slot.wake())
+            })
+            .collect();
+        let mut scheduled: Vec<_> = slot.put(1).into_iter().collect();
+        scheduled.extend(ths.into_iter().filter_map(|th| th.join().unwrap()));
+        assert_eq!(scheduled, [1]);
+    });
+}
@@ -0,0 +1,5 @@
+//! Model checked tests of the scheduler primitives, see `crate::loom`.
+
+mod loom_idle;
+mod loom_park;
+mod loom_task_waker;
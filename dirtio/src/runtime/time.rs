@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+/// Key of a timer, ordered by deadline then by registration.
+pub(crate) type TimerKey = (Instant, u64);
+
+/// The timers of a runtime, fired by the thread polling the IO driver.
+pub(crate) struct Timers {
+    clock: Clock,
+    entries: Mutex<BTreeMap<TimerKey, Waker>>,
+    next_id: AtomicU64,
+}
+
+/// Source of the current time.
+enum Clock {
+    /// The system clock.
+    Real,
+    /// Follows the system clock until paused, then only advances when told
+    /// to.
+    Virtual(Mutex<VirtualClock>),
+}
+
+struct VirtualClock {
+    /// The time when last paused or resumed.
+    base: Instant,
+    /// The system time when resumed, `None` while paused.
+    resumed_at: Option<Instant>,
+}
+
+impl VirtualClock {
+    fn now(&self) -> Instant {
+        match self.resumed_at {
+            Some(resumed_at) => self.base + resumed_at.elapsed(),
+            None => self.base,
+        }
+    }
+}
+
+impl Timers {
+    /// Create the timers, the clock can be paused if `pausable`.
+    pub(crate) fn new(pausable: bool, paused: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            clock: if pausable {
+                Clock::Virtual(Mutex::new(VirtualClock {
+                    base: now,
+                    resumed_at: (!paused).then_some(now),
+                }))
+            } else {
+                Clock::Real
+            },
+            entries: Mutex::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        match &self.clock {
+            Clock::Real => Instant::now(),
+            Clock::Virtual(clock) => clock.lock().unwrap().now(),
+        }
+    }
+
+    /// Register a timer, or update the waker of the timer `key` if it's
+    /// still registered.
+    pub(crate) fn register(
+        &self,
+        key: Option<TimerKey>,
+        deadline: Instant,
+        waker: &Waker,
+    ) -> TimerKey {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = key.and_then(|key| entries.get_mut(&key)) {
+            if !entry.will_wake(waker) {
+                *entry = waker.clone();
+            }
+            return key.unwrap();
+        }
+
+        let key = (deadline, self.next_id.fetch_add(1, Ordering::Relaxed));
+        entries.insert(key, waker.clone());
+        key
+    }
+
+    pub(crate) fn remove(&self, key: TimerKey) {
+        // Dropped out of the lock, the task may go with the waker.
+        let waker = self.entries.lock().unwrap().remove(&key);
+        drop(waker);
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .first_key_value()
+            .map(|((deadline, _), _)| *deadline)
+    }
+
+    /// Shorten the time to block in the driver so that the next timer
+    /// fires in time. Never blocks for a timer while paused, the clock is
+    /// advanced instead.
+    pub(crate) fn timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let Some(deadline) = self.next_deadline() else {
+            return timeout;
+        };
+        if self.is_paused() {
+            return Some(Duration::ZERO);
+        }
+        let until = deadline.saturating_duration_since(self.now());
+        Some(timeout.map_or(until, |timeout| timeout.min(until)))
+    }
+
+    /// Remove all the timers when the runtime shuts down, returns their
+    /// wakers.
+    pub(crate) fn clear(&self) -> Vec<Waker> {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        entries.into_values().collect()
+    }
+
+    /// Wake the expired timers.
+    pub(crate) fn process(&self) {
+        for waker in self.expired() {
+            waker.wake();
+        }
+    }
+
+    /// Remove the expired timers, returns their wakers in deadline order.
+    pub(crate) fn expired(&self) -> Vec<Waker> {
+        let now = self.now();
+        let mut entries = self.entries.lock().unwrap();
+        let mut expired = Vec::new();
+        while let Some(entry) = entries.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            expired.push(entry.remove());
+        }
+        expired
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        match &self.clock {
+            Clock::Real => false,
+            Clock::Virtual(clock) => clock.lock().unwrap().resumed_at.is_none(),
+        }
+    }
+
+    /// Advance the paused clock to the next timer and fire it, returns
+    /// `false` if not paused or there is no timer.
+    pub(crate) fn auto_advance(&self) -> bool {
+        if !self.is_paused() {
+            return false;
+        }
+        let Some(deadline) = self.next_deadline() else {
+            return false;
+        };
+        self.advance_to(deadline);
+        self.process();
+        true
+    }
+
+    /// Advance the paused clock to `instant`, it never goes backwards.
+    pub(crate) fn advance_to(&self, instant: Instant) {
+        let mut clock = self.paused_clock();
+        clock.base = clock.base.max(instant);
+    }
+
+    /// Pause the clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock can't be paused or is already paused.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn pause(&self) {
+        let Clock::Virtual(clock) = &self.clock else {
+            panic!("time can only be paused on the current thread runtime");
+        };
+        let mut clock = clock.lock().unwrap();
+        assert!(clock.resumed_at.is_some(), "time is already paused");
+        clock.base = clock.now();
+        clock.resumed_at = None;
+    }
+
+    /// Resume the paused clock, it continues from the paused time.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn resume(&self) {
+        let mut clock = self.paused_clock();
+        clock.resumed_at = Some(Instant::now());
+    }
+
+    /// Advance the paused clock by `duration` and fire the expired timers.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn advance(&self, duration: Duration) {
+        let mut clock = self.paused_clock();
+        clock.base += duration;
+        drop(clock);
+        self.process();
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the clock is not paused.
+    fn paused_clock(&self) -> MutexGuard<'_, VirtualClock> {
+        let Clock::Virtual(clock) = &self.clock else {
+            panic!("time is not paused");
+        };
+        let clock = clock.lock().unwrap();
+        assert!(clock.resumed_at.is_none(), "time is not paused");
+        clock
+    }
+}
@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::Location;
+use std::task::Poll;
+
+thread_local! {
+    /// Locations of the `Traced` futures being polled, innermost last.
+    static FRAMES: RefCell<Vec<&'static Location<'static>>> = const { RefCell::new(Vec::new()) };
+    /// Await chains of the pending futures in current task poll, `None`
+    /// outside of a task poll.
+    static CAPTURED: RefCell<Option<Vec<Backtrace>>> = const { RefCell::new(None) };
+}
+
+/// Await points of a task at the end of its last poll, one await chain
+/// for each pending leaf future.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    backtraces: Vec<Backtrace>,
+}
+
+#[derive(Clone, Debug)]
+struct Backtrace {
+    frames: Vec<&'static Location<'static>>,
+    /// The future returning pending, `None` if not known to dirtio.
+    leaf: Option<&'static str>,
+}
+
+impl Trace {
+    /// Returns `true` if nothing was captured, the task awaits no
+    /// `Traced` future nor any leaf future of dirtio.
+    pub fn is_empty(&self) -> bool {
+        self.backtraces.is_empty()
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, backtrace) in self.backtraces.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for location in &backtrace.frames {
+                writeln!(f, "  await at {}", location)?;
+            }
+            match backtrace.leaf {
+                Some(leaf) => writeln!(f, "  pending on {}", leaf)?,
+                None => writeln!(f, "  pending")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run a poll of a task, capturing the await chains of its pending
+/// futures.
+pub(crate) fn capture<R>(f: impl FnOnce() -> R) -> (R, Trace) {
+    let prev = CAPTURED.with(|c| c.replace(Some(Vec::new())));
+    let _guard = OnExit(Some(move || {
+        CAPTURED.with(|c| *c.borrow_mut() = prev);
+    }));
+
+    let res = f();
+    let backtraces = CAPTURED
+        .with(|c| c.borrow_mut().as_mut().map(std::mem::take))
+        .unwrap_or_default();
+    (res, Trace { backtraces })
+}
+
+/// Poll within a frame of `Traced`, records the chain ending at the frame
+/// if the inner future returns pending without reaching a leaf.
+pub(crate) fn frame<T>(
+    location: &'static Location<'static>,
+    poll: impl FnOnce() -> Poll<T>,
+) -> Poll<T> {
+    if CAPTURED.with(|c| c.borrow().is_none()) {
+        return poll();
+    }
+    FRAMES.with(|frames| frames.borrow_mut().push(location));
+    let _guard = OnExit(Some(|| {
+        FRAMES.with(|frames| frames.borrow_mut().pop());
+    }));
+
+    let before = captured_len();
+    let res = poll();
+    if res.is_pending() && captured_len() == before {
+        record(None);
+    }
+    res
+}
+
+/// Record the await chain of a leaf future returning pending.
+pub(crate) fn leaf(name: &'static str) {
+    record(Some(name));
+}
+
+fn record(leaf: Option<&'static str>) {
+    CAPTURED.with(|c| {
+        if let Some(captured) = c.borrow_mut().as_mut() {
+            let frames = FRAMES.with(|frames| frames.borrow().clone());
+            captured.push(Backtrace { frames, leaf });
+        }
+    });
+}
+
+fn captured_len() -> usize {
+    CAPTURED.with(|c| c.borrow().as_ref().map_or(0, Vec::len))
+}
+
+/// Restore the thread locals even if the poll panics.
+struct OnExit<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for OnExit<F> {
+    fn drop(&mut self) {
+        if let Some(f) = self.0.take() {
+            f();
+        }
+    }
+}
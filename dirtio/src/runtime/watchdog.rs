@@ -0,0 +1,131 @@
+//! Watchdog reporting the polls which block a worker for too long, set by
+//! `Builder::warn_on_slow_poll`.
+//!
+//! The stack of another thread can't be captured portably, the report
+//! names the blocked thread instead. When the slow poll returns, the
+//! worker reports its duration and the await points the task stopped at.
+
+use super::scheduler::handle::HandleInner;
+use super::scheduler::task::Header;
+use super::trace::Trace;
+
+use crate::task::Id;
+
+use std::fmt::{self, Write};
+use std::io;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Max interval of checking the workers.
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+thread_local! {
+    /// Name of the thread, captured once for all the polls it runs.
+    static THREAD_NAME: Option<Arc<str>> = thread::current().name().map(Arc::from);
+}
+
+/// Report a problem with a task, as a `tracing` warning with the
+/// `tracing` feature, on stderr otherwise.
+pub(crate) fn report(task: Id, msg: fmt::Arguments<'_>) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(task.id = %task, "{}", msg);
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = task;
+        eprintln!("dirtio: {}", msg);
+    }
+}
+
+/// The poll running on a worker.
+#[derive(Default)]
+pub(crate) struct PollWatch {
+    current: Mutex<Option<Polling>>,
+}
+
+struct Polling {
+    header: Arc<Header>,
+    start: Instant,
+    thread: Option<Arc<str>>,
+    /// Set once the watchdog reported the poll.
+    reported: bool,
+}
+
+impl PollWatch {
+    /// Start watching a poll of the task.
+    pub(crate) fn enter(&self, header: &Arc<Header>) {
+        *self.current.lock().unwrap() = Some(Polling {
+            header: header.clone(),
+            start: Instant::now(),
+            thread: THREAD_NAME.try_with(Clone::clone).ok().flatten(),
+            reported: false,
+        });
+    }
+
+    /// Stop watching the poll, `trace` holds the await points it
+    /// returned at.
+    pub(crate) fn exit(&self, worker: usize, trace: &Trace) {
+        let Some(polling) = self.current.lock().unwrap().take() else {
+            return;
+        };
+        if !polling.reported {
+            return;
+        }
+        let mut msg = format!(
+            "{} blocked worker {} for {:?}",
+            polling.header,
+            worker,
+            polling.start.elapsed()
+        );
+        if !trace.is_empty() {
+            let _ = write!(msg, ", then returned at:\n{}", trace);
+        }
+        report(polling.header.id, format_args!("{}", msg.trim_end()));
+    }
+
+    /// Report the poll if running longer than `threshold`, once.
+    fn check(&self, worker: usize, threshold: Duration) {
+        let mut current = self.current.lock().unwrap();
+        let Some(polling) = current.as_mut().filter(|polling| !polling.reported) else {
+            return;
+        };
+        let elapsed = polling.start.elapsed();
+        if elapsed < threshold {
+            return;
+        }
+        polling.reported = true;
+        report(
+            polling.header.id,
+            format_args!(
+                "{} has been polled on worker {} (thread {}) for {:?}, \
+                 it may be blocking the thread",
+                polling.header,
+                worker,
+                polling.thread.as_deref().unwrap_or("<unnamed>"),
+                elapsed,
+            ),
+        );
+    }
+}
+
+/// Check the workers in a background thread, which exits when the
+/// runtime shuts down.
+pub(crate) fn spawn(threshold: Duration, handle: Weak<HandleInner>) -> io::Result<()> {
+    let interval = (threshold / 2).clamp(Duration::from_millis(1), CHECK_INTERVAL);
+    thread::Builder::new()
+        .name("dirtio-watchdog".to_string())
+        .spawn(move || loop {
+            let Some(handle) = handle.upgrade() else {
+                return;
+            };
+            if handle.is_shutdown() {
+                return;
+            }
+            for (worker, watch) in handle.shared.watches.iter().enumerate() {
+                watch.check(worker, threshold);
+            }
+            drop(handle);
+            thread::sleep(interval);
+        })?;
+    Ok(())
+}
@@ -0,0 +1,20 @@
+//! Deterministic simulation of a runtime.
+//!
+//! A simulation runs every task on the thread calling `block_on`, one
+//! seeded RNG picks which ready task runs next and when the IO events are
+//! dispatched. Time is virtual, it jumps to the next timer when all tasks
+//! are idle, and the timers expiring together fire in random order. A run
+//! replays exactly from its seed.
+//!
+//! The seed is taken from `Builder::seed`, or the `DIRTIO_SEED` environment
+//! variable, otherwise it's random. It's printed when the simulation
+//! panics, to replay the failure.
+//!
+//! The sockets of `dirtio::net` are backed by a simulated network, see
+//! `net`. Events of other IO sources arrive when the system delivers them,
+//! which is not deterministic.
+
+pub mod net;
+
+mod runtime;
+pub use runtime::{Builder, Runtime};
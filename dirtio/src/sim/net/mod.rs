@@ -0,0 +1,334 @@
+//! Simulated network backing `dirtio::net` in a simulation.
+//!
+//! The sockets created in a simulation are in-memory, the IP address of a
+//! socket is the host it belongs to. Each packet is delayed by the latency
+//! of the network, on the virtual clock. UDP datagrams may be lost, and
+//! reordered when the latency varies. TCP keeps the order and never loses
+//! data, a partition stalls the connections until it's repaired.
+//!
+//! Sockets bound to an unspecified address, and outgoing connections, are
+//! on the host of current future, see `host`.
+
+mod tcp;
+pub(crate) use tcp::{TcpListener, TcpStream};
+
+mod udp;
+pub(crate) use udp::UdpSocket;
+
+use crate::runtime::context;
+use crate::runtime::rng::Rng;
+use crate::runtime::scheduler::handle::Handle;
+use crate::runtime::time::{TimerKey, Timers};
+use crate::task::Id;
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures::Future;
+
+/// First ephemeral port.
+const EPHEMERAL_PORT: u16 = 49152;
+
+thread_local! {
+    /// The host of the future being polled.
+    static HOST: Cell<Option<IpAddr>> = const { Cell::new(None) };
+}
+
+/// Controls the simulated network of current simulation.
+#[derive(Clone)]
+pub struct Network {
+    net: Arc<Net>,
+}
+
+impl Network {
+    /// Returns the network of current simulation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a simulation.
+    pub fn current() -> Self {
+        let handle = Handle::current();
+        let net = handle.config.net.clone();
+        Self {
+            net: net.expect("called outside of a simulation"),
+        }
+    }
+
+    pub(crate) fn new(net: Arc<Net>) -> Self {
+        Self { net }
+    }
+
+    /// Delay each packet by a latency picked in `min..=max`, zero by
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn set_latency(&self, min: Duration, max: Duration) {
+        assert!(min <= max, "min latency is greater than max");
+        self.net.state().latency = (min, max);
+    }
+
+    /// Drop each UDP datagram with `probability`, zero by default.
+    pub fn set_loss(&self, probability: f64) {
+        self.net.state().loss = probability;
+    }
+
+    /// Cut the link between the hosts `a` and `b`, in both directions.
+    pub fn partition(&self, a: IpAddr, b: IpAddr) {
+        self.net.state().partitions.insert(link(a, b));
+    }
+
+    /// Restore the link between the hosts `a` and `b`.
+    pub fn repair(&self, a: IpAddr, b: IpAddr) {
+        let mut state = self.net.state();
+        state.partitions.remove(&link(a, b));
+        state.wake_all();
+    }
+
+    /// Restore all the links.
+    pub fn repair_all(&self) {
+        let mut state = self.net.state();
+        state.partitions.clear();
+        state.wake_all();
+    }
+}
+
+/// Run the future as the host `ip`.
+///
+/// The tasks spawned by the future are not on the host, wrap them too.
+pub fn host<F>(ip: IpAddr, fut: F) -> Host<F>
+where
+    F: Future,
+{
+    Host { ip, inner: fut }
+}
+
+/// Future returned by `host`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Host<F> {
+    ip: IpAddr,
+    inner: F,
+}
+
+impl<F> Future for Host<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let prev = HOST.with(|host| host.replace(Some(self.ip)));
+        // Safety: `inner` is never moved out of the pinned `Host`.
+        let inner = unsafe { self.map_unchecked_mut(|this| &mut this.inner) };
+        let res = inner.poll(cx);
+        HOST.with(|host| host.set(prev));
+        res
+    }
+}
+
+/// Returns the current runtime if it's a simulation.
+pub(crate) fn current() -> Option<Handle> {
+    context::try_current()
+        .ok()
+        .filter(|handle| handle.config.simulation)
+}
+
+/// The host of current future, localhost by default.
+fn current_host() -> IpAddr {
+    HOST.with(Cell::get)
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+fn link(a: IpAddr, b: IpAddr) -> (IpAddr, IpAddr) {
+    (a.min(b), a.max(b))
+}
+
+/// State of the simulated network.
+pub(crate) struct Net {
+    state: Mutex<State>,
+}
+
+impl Net {
+    pub(crate) fn new(rng: Rng) -> Self {
+        Self {
+            state: Mutex::new(State {
+                rng,
+                latency: (Duration::ZERO, Duration::ZERO),
+                loss: 0.0,
+                partitions: HashSet::new(),
+                next_port: EPHEMERAL_PORT,
+                next_id: 0,
+                listeners: BTreeMap::new(),
+                conns: BTreeMap::new(),
+                udp: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Returns the network of the simulation.
+fn net(handle: &Handle) -> &Net {
+    handle.config.net.as_deref().unwrap()
+}
+
+struct State {
+    rng: Rng,
+    latency: (Duration, Duration),
+    loss: f64,
+    partitions: HashSet<(IpAddr, IpAddr)>,
+    next_port: u16,
+    next_id: u64,
+    // Ordered, so that `wake_all` wakes the sockets in the same order on
+    // each run of a seed.
+    listeners: BTreeMap<SocketAddr, tcp::Listener>,
+    conns: BTreeMap<u64, tcp::Conn>,
+    udp: BTreeMap<SocketAddr, udp::Socket>,
+}
+
+impl State {
+    /// Pick the latency of a packet.
+    fn latency(&mut self) -> Duration {
+        let (min, max) = self.latency;
+        let range = (max - min).as_nanos() as u64;
+        if range == 0 {
+            return min;
+        }
+        min + Duration::from_nanos(self.rng.next_u64() % (range + 1))
+    }
+
+    fn partitioned(&self, a: IpAddr, b: IpAddr) -> bool {
+        self.partitions.contains(&link(a, b))
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Resolve the address to bind, the unspecified address is current
+    /// host and port `0` an ephemeral port.
+    fn bind_addr(&mut self, mut addr: SocketAddr) -> io::Result<SocketAddr> {
+        if addr.ip().is_unspecified() {
+            addr.set_ip(current_host());
+        }
+        if addr.port() == 0 {
+            addr.set_port(self.ephemeral_port(addr.ip()));
+        } else if self.listeners.contains_key(&addr) || self.udp.contains_key(&addr) {
+            return Err(io::ErrorKind::AddrInUse.into());
+        }
+        Ok(addr)
+    }
+
+    fn ephemeral_port(&mut self, ip: IpAddr) -> u16 {
+        loop {
+            let port = self.next_port;
+            self.next_port = self.next_port.checked_add(1).unwrap_or(EPHEMERAL_PORT);
+            let addr = SocketAddr::new(ip, port);
+            if !self.listeners.contains_key(&addr) && !self.udp.contains_key(&addr) {
+                return port;
+            }
+        }
+    }
+
+    /// Wake all the waiting sockets, after the links changed.
+    fn wake_all(&mut self) {
+        self.listeners.values_mut().for_each(|l| l.waiter.wake());
+        for conn in self.conns.values_mut() {
+            conn.dirs.iter_mut().for_each(|dir| dir.waiter.wake());
+        }
+        self.udp.values_mut().for_each(|s| s.waiter.wake());
+    }
+}
+
+/// The tasks waiting on a socket, woken by the peer or at the arrival of
+/// the next packet. Several tasks may wait on the same socket.
+#[derive(Default)]
+struct Waiter {
+    tasks: Vec<Waiting>,
+}
+
+/// A task waiting on a socket, with its own timer so that each task is
+/// woken at the arrival.
+///
+/// Keyed by the id of the task, `None` outside of a task, as a task gets
+/// a new waker on each poll.
+struct Waiting {
+    task: Option<Id>,
+    waker: Waker,
+    timer: Option<TimerKey>,
+}
+
+impl Waiter {
+    fn wait(&mut self, timers: &Timers, waker: &Waker, arrival: Option<Instant>) {
+        let task = match self.position() {
+            Some(i) => {
+                let task = &mut self.tasks[i];
+                if !task.waker.will_wake(waker) {
+                    task.waker = waker.clone();
+                }
+                task
+            }
+            None => {
+                self.tasks.push(Waiting {
+                    task: context::current_task(),
+                    waker: waker.clone(),
+                    timer: None,
+                });
+                self.tasks.last_mut().unwrap()
+            }
+        };
+        match (task.timer, arrival) {
+            (Some(key), Some(arrival)) if key.0 == arrival => {
+                timers.register(Some(key), arrival, waker);
+            }
+            _ => {
+                if let Some(key) = task.timer.take() {
+                    timers.remove(key);
+                }
+                task.timer = arrival.map(|arrival| timers.register(None, arrival, waker));
+            }
+        }
+    }
+
+    /// Wake all the waiting tasks, those still pending wait again.
+    fn wake(&mut self) {
+        self.tasks.iter().for_each(|task| task.waker.wake_by_ref());
+    }
+
+    /// Current task is done waiting, the others are woken by their timers
+    /// to check the next packet.
+    fn done(&mut self, timers: &Timers) {
+        if let Some(i) = self.position() {
+            let task = self.tasks.swap_remove(i);
+            if let Some(key) = task.timer {
+                timers.remove(key);
+            }
+        }
+    }
+
+    /// Position of current task.
+    fn position(&self) -> Option<usize> {
+        let current = context::current_task();
+        self.tasks.iter().position(|task| task.task == current)
+    }
+
+    /// Remove the timers when the socket is dropped.
+    fn clear(&mut self, timers: &Timers) {
+        for task in &mut self.tasks {
+            if let Some(key) = task.timer.take() {
+                timers.remove(key);
+            }
+        }
+    }
+}
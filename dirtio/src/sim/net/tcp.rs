@@ -0,0 +1,295 @@
+use super::{current_host, link, net, Waiter};
+
+use crate::runtime::scheduler::handle::Handle;
+use crate::runtime::{coop, trace};
+
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+/// A listening socket in the network state.
+pub(super) struct Listener {
+    /// Arrival time and id of the incoming connections.
+    backlog: VecDeque<(Instant, u64)>,
+    pub(super) waiter: Waiter,
+}
+
+/// A connection in the network state, side `0` connected to side `1`.
+pub(super) struct Conn {
+    addrs: [SocketAddr; 2],
+    /// The data flowing to each side.
+    pub(super) dirs: [Dir; 2],
+}
+
+#[derive(Default)]
+pub(super) struct Dir {
+    segments: VecDeque<Segment>,
+    pub(super) waiter: Waiter,
+    /// The end of stream is queued.
+    closed: bool,
+    /// The side reading it was dropped.
+    reader_dropped: bool,
+}
+
+struct Segment {
+    arrival: Instant,
+    /// Empty at the end of stream.
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Conn {
+    /// The link of the connection is cut by a partition.
+    fn cut(&self, partitions: &HashSet<(IpAddr, IpAddr)>) -> bool {
+        partitions.contains(&link(self.addrs[0].ip(), self.addrs[1].ip()))
+    }
+}
+
+impl Dir {
+    /// Queue the data after the segments in flight, which keeps the order.
+    fn push(&mut self, arrival: Instant, data: Vec<u8>) {
+        let arrival = self
+            .segments
+            .back()
+            .map_or(arrival, |last| last.arrival.max(arrival));
+        self.segments.push_back(Segment {
+            arrival,
+            data,
+            pos: 0,
+        });
+        self.waiter.wake();
+    }
+
+    fn close(&mut self, arrival: Instant) {
+        if !self.closed {
+            self.closed = true;
+            self.push(arrival, Vec::new());
+        }
+    }
+}
+
+pub(crate) struct TcpListener {
+    handle: Handle,
+    addr: SocketAddr,
+}
+
+impl TcpListener {
+    pub(crate) fn bind(handle: Handle, addr: SocketAddr) -> io::Result<Self> {
+        let mut state = net(&handle).state();
+        let addr = state.bind_addr(addr)?;
+        state.listeners.insert(
+            addr,
+            Listener {
+                backlog: VecDeque::new(),
+                waiter: Waiter::default(),
+            },
+        );
+        drop(state);
+        Ok(Self { handle, addr })
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub(crate) fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<(TcpStream, SocketAddr)> {
+        // Yield if current task used up its budget, like a real socket.
+        let mut coop = ready!(coop::poll_proceed(cx));
+        let timers = &self.handle.timers;
+        let now = timers.now();
+        let mut state = net(&self.handle).state();
+        let state = &mut *state;
+
+        let listener = state.listeners.get_mut(&self.addr).unwrap();
+        let ready = listener
+            .backlog
+            .iter()
+            .position(|&(arrival, id)| arrival <= now && !state.conns[&id].cut(&state.partitions));
+        if let Some(i) = ready {
+            let (_, id) = listener.backlog.remove(i).unwrap();
+            listener.waiter.done(timers);
+            coop.made_progress();
+            let peer = state.conns[&id].addrs[0];
+            let stream = TcpStream {
+                handle: self.handle.clone(),
+                id,
+                side: 1,
+                local: self.addr,
+                peer,
+            };
+            return Poll::Ready((stream, peer));
+        }
+
+        let arrival = listener
+            .backlog
+            .iter()
+            .map(|&(arrival, _)| arrival)
+            .filter(|&arrival| arrival > now)
+            .min();
+        listener.waiter.wait(timers, cx.waker(), arrival);
+        trace::leaf("simulated tcp accept");
+        Poll::Pending
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        let timers = &self.handle.timers;
+        let now = timers.now();
+        let mut state = net(&self.handle).state();
+        let Some(mut listener) = state.listeners.remove(&self.addr) else {
+            return;
+        };
+        listener.waiter.clear(timers);
+
+        // Refuse the connections not accepted.
+        for &(_, id) in &listener.backlog {
+            let conn = state.conns.get_mut(&id).unwrap();
+            conn.dirs[0].close(now);
+            conn.dirs[1].reader_dropped = true;
+        }
+        // Drop the wakers out of the lock, the tasks may go with them.
+        drop(state);
+    }
+}
+
+pub(crate) struct TcpStream {
+    handle: Handle,
+    id: u64,
+    side: usize,
+    local: SocketAddr,
+    peer: SocketAddr,
+}
+
+impl TcpStream {
+    pub(crate) fn connect(handle: Handle, addr: SocketAddr) -> io::Result<Self> {
+        let now = handle.timers.now();
+        let mut state = net(&handle).state();
+        if !state.listeners.contains_key(&addr) {
+            return Err(io::ErrorKind::ConnectionRefused.into());
+        }
+
+        let host = current_host();
+        let local = SocketAddr::new(host, state.ephemeral_port(host));
+        let id = state.next_id();
+        let arrival = now + state.latency();
+        state.conns.insert(
+            id,
+            Conn {
+                addrs: [local, addr],
+                dirs: Default::default(),
+            },
+        );
+        let listener = state.listeners.get_mut(&addr).unwrap();
+        listener.backlog.push_back((arrival, id));
+        listener.waiter.wake();
+        drop(state);
+
+        Ok(Self {
+            handle,
+            id,
+            side: 0,
+            local,
+            peer: addr,
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    pub(crate) fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    pub(crate) fn poll_read(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut coop = ready!(coop::poll_proceed(cx));
+        let timers = &self.handle.timers;
+        let now = timers.now();
+        let mut state = net(&self.handle).state();
+        let state = &mut *state;
+
+        let cut = state.conns[&self.id].cut(&state.partitions);
+        let dir = &mut state.conns.get_mut(&self.id).unwrap().dirs[self.side];
+        let front = dir.segments.front_mut().filter(|_| !cut);
+        let arrival = match front {
+            Some(segment) if segment.arrival <= now => {
+                dir.waiter.done(timers);
+                coop.made_progress();
+                // The end of stream is kept, it's read again.
+                if segment.data.is_empty() {
+                    return Poll::Ready(Ok(0));
+                }
+                let data = &segment.data[segment.pos..];
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                segment.pos += n;
+                if segment.pos == segment.data.len() {
+                    dir.segments.pop_front();
+                }
+                return Poll::Ready(Ok(n));
+            }
+            Some(segment) => Some(segment.arrival),
+            None => None,
+        };
+
+        dir.waiter.wait(timers, cx.waker(), arrival);
+        trace::leaf("simulated tcp read");
+        Poll::Pending
+    }
+
+    pub(crate) fn poll_write(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let mut coop = ready!(coop::poll_proceed(cx));
+        coop.made_progress();
+        Poll::Ready(self.write(buf))
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let now = self.handle.timers.now();
+        let mut state = net(&self.handle).state();
+        let latency = state.latency();
+        let dir = &mut state.conns.get_mut(&self.id).unwrap().dirs[1 - self.side];
+        if dir.reader_dropped || dir.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if !buf.is_empty() {
+            dir.push(now + latency, buf.to_vec());
+        }
+        Ok(buf.len())
+    }
+
+    /// Shut down the write half, the peer reads the end of stream after
+    /// the data in flight.
+    pub(crate) fn shutdown(&self) {
+        let now = self.handle.timers.now();
+        let mut state = net(&self.handle).state();
+        let latency = state.latency();
+        let dir = &mut state.conns.get_mut(&self.id).unwrap().dirs[1 - self.side];
+        dir.close(now + latency);
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        self.shutdown();
+
+        let timers = &self.handle.timers;
+        let mut state = net(&self.handle).state();
+        let conn = state.conns.get_mut(&self.id).unwrap();
+        let dir = &mut conn.dirs[self.side];
+        dir.reader_dropped = true;
+        dir.waiter.clear(timers);
+        if conn.dirs.iter().all(|dir| dir.reader_dropped) {
+            let conn = state.conns.remove(&self.id);
+            // Dropped out of the lock, the tasks may go with the wakers.
+            drop(state);
+            drop(conn);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::{link, net, Waiter};
+
+use crate::runtime::scheduler::handle::Handle;
+use crate::runtime::{coop, trace};
+
+use std::io;
+use std::net::SocketAddr;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+/// A UDP socket in the network state.
+pub(super) struct Socket {
+    inbox: Vec<Datagram>,
+    pub(super) waiter: Waiter,
+    /// Only the datagrams from the peer are received if connected.
+    peer: Option<SocketAddr>,
+}
+
+struct Datagram {
+    arrival: Instant,
+    /// Orders the datagrams arriving together.
+    seq: u64,
+    from: SocketAddr,
+    data: Vec<u8>,
+}
+
+pub(crate) struct UdpSocket {
+    handle: Handle,
+    addr: SocketAddr,
+}
+
+impl UdpSocket {
+    pub(crate) fn bind(handle: Handle, addr: SocketAddr) -> io::Result<Self> {
+        let mut state = net(&handle).state();
+        let addr = state.bind_addr(addr)?;
+        state.udp.insert(
+            addr,
+            Socket {
+                inbox: Vec::new(),
+                waiter: Waiter::default(),
+                peer: None,
+            },
+        );
+        drop(state);
+        Ok(Self { handle, addr })
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub(crate) fn connect(&self, addr: SocketAddr) {
+        let mut state = net(&self.handle).state();
+        state.udp.get_mut(&self.addr).unwrap().peer = Some(addr);
+    }
+
+    pub(crate) fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        // Yield if current task used up its budget, like a real socket.
+        let mut coop = ready!(coop::poll_proceed(cx));
+        coop.made_progress();
+        Poll::Ready(self.send(buf))
+    }
+
+    fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let state = net(&self.handle).state();
+        let peer = state.udp[&self.addr].peer;
+        drop(state);
+        match peer {
+            Some(peer) => self.send_to(buf, peer),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Send a datagram, it's silently lost if nothing is bound to
+    /// `target`, or if the link is cut.
+    pub(crate) fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        let mut coop = ready!(coop::poll_proceed(cx));
+        coop.made_progress();
+        Poll::Ready(self.send_to(buf, target))
+    }
+
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+        let now = self.handle.timers.now();
+        let mut state = net(&self.handle).state();
+        let loss = state.loss;
+        let lost = state.rng.chance(loss) || state.partitioned(self.addr.ip(), target.ip());
+        let latency = state.latency();
+        let seq = state.next_id();
+        if let Some(socket) = state.udp.get_mut(&target).filter(|_| !lost) {
+            socket.inbox.push(Datagram {
+                arrival: now + latency,
+                seq,
+                from: self.addr,
+                data: buf.to_vec(),
+            });
+            socket.waiter.wake();
+        }
+        Ok(buf.len())
+    }
+
+    pub(crate) fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut coop = ready!(coop::poll_proceed(cx));
+        let timers = &self.handle.timers;
+        let now = timers.now();
+        let mut state = net(&self.handle).state();
+        let state = &mut *state;
+
+        let socket = state.udp.get_mut(&self.addr).unwrap();
+        // The datagrams in flight over a cut link are lost.
+        let ip = self.addr.ip();
+        let peer = socket.peer;
+        socket.inbox.retain(|datagram| {
+            !state.partitions.contains(&link(datagram.from.ip(), ip))
+                && peer.is_none_or(|peer| peer == datagram.from)
+        });
+
+        let next = socket
+            .inbox
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, datagram)| (datagram.arrival, datagram.seq));
+        let arrival = match next {
+            Some((i, datagram)) if datagram.arrival <= now => {
+                let datagram = socket.inbox.swap_remove(i);
+                socket.waiter.done(timers);
+                coop.made_progress();
+                // The rest of the datagram is discarded, like a real socket.
+                let n = datagram.data.len().min(buf.len());
+                buf[..n].copy_from_slice(&datagram.data[..n]);
+                return Poll::Ready(Ok((n, datagram.from)));
+            }
+            Some((_, datagram)) => Some(datagram.arrival),
+            None => None,
+        };
+
+        socket.waiter.wait(timers, cx.waker(), arrival);
+        trace::leaf("simulated udp recv");
+        Poll::Pending
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        let socket = net(&self.handle).state().udp.remove(&self.addr);
+        // Dropped out of the lock, the tasks may go with the wakers.
+        if let Some(mut socket) = socket {
+            socket.waiter.clear(&self.handle.timers);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use super::net::{Net, Network};
+
+use crate::io::driver::Driver;
+use crate::runtime::config::Config;
+use crate::runtime::context;
+use crate::runtime::coop;
+use crate::runtime::handle::Handle;
+use crate::runtime::rng::{self, Rng};
+use crate::runtime::scheduler::worker::Worker;
+use crate::runtime::scheduler::{self, Flavor, Task};
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{self, Wake};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::{pin_mut, Future};
+
+/// Environment variable to set the seed of the simulations.
+const SEED_ENV: &str = "DIRTIO_SEED";
+
+/// Probability of dispatching the IO events after running a task.
+const IO_PROBABILITY: f64 = 0.1;
+
+/// Builds a simulated runtime.
+#[derive(Default)]
+pub struct Builder {
+    seed: Option<u64>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed of the RNG driving the simulation.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the seed is not given and `DIRTIO_SEED` is not a number.
+    pub fn build(&mut self) -> io::Result<Runtime> {
+        let seed = self.seed.unwrap_or_else(|| match std::env::var(SEED_ENV) {
+            Ok(seed) => seed
+                .parse()
+                .unwrap_or_else(|_| panic!("{} is not a number: {:?}", SEED_ENV, seed)),
+            Err(_) => random_seed(),
+        });
+
+        let mut rng = Rng::new(seed);
+        let net = Arc::new(Net::new(Rng::new(rng.next_u64())));
+        let config = Config {
+            start_paused: true,
+            simulation: true,
+            net: Some(net.clone()),
+            ..Config::default()
+        };
+        let (mut workers, handle) =
+            Worker::create(1, vec![Driver::new()?], Flavor::CurrentThread, config);
+        let worker = workers.pop().unwrap();
+        let start = handle.timers.now();
+
+        Ok(Runtime {
+            handle: Handle { inner: handle },
+            worker,
+            net,
+            rng,
+            seed,
+            start,
+            ready: Vec::new(),
+        })
+    }
+}
+
+/// A runtime driven by a seeded RNG, see the module docs.
+pub struct Runtime {
+    handle: Handle,
+    worker: Worker,
+    net: Arc<Net>,
+    rng: Rng,
+    seed: u64,
+    /// Virtual time at creation.
+    start: Instant,
+    /// The tasks ready to run, picked at random.
+    ready: Vec<Task>,
+}
+
+impl Runtime {
+    /// Build a simulation with the seed.
+    pub fn new(seed: u64) -> io::Result<Self> {
+        Builder::new().seed(seed).build()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Returns the simulated network.
+    pub fn network(&self) -> Network {
+        Network::new(self.net.clone())
+    }
+
+    /// Returns the virtual time elapsed since the simulation was built.
+    pub fn elapsed(&self) -> Duration {
+        self.handle.inner.timers.now() - self.start
+    }
+
+    /// Run a future to completion, along with the tasks it spawns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a runtime, or if all the tasks are
+    /// idle with no timer or IO source to wake them.
+    pub fn block_on<F>(&mut self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        let inner = self.handle.inner.clone();
+        inner.check_shutdown();
+        let _runtime = context::enter_runtime();
+        let _guard = inner.enter();
+        let _report = ReportSeed(self.seed);
+        // `select!` picks the branch to poll first at random.
+        rng::seed_thread(self.rng.next_u64());
+
+        let notified = Arc::new(Notified {
+            handle: inner.clone(),
+            notified: AtomicBool::new(true),
+        });
+        let waker = task::Waker::from(notified.clone());
+        let mut cx = task::Context::from_waker(&waker);
+
+        pin_mut!(fut);
+
+        loop {
+            inner.check_shutdown();
+            while let Some(task) = self.worker.pop_task() {
+                self.ready.push(task);
+            }
+
+            // The future passed in takes the last slot.
+            let main = notified.notified.load(Ordering::SeqCst);
+            let n = self.ready.len() + main as usize;
+            if n > 0 {
+                let i = self.rng.below(n);
+                if i == self.ready.len() {
+                    notified.notified.store(false, Ordering::SeqCst);
+                    if let task::Poll::Ready(r) = coop::budget(|| fut.as_mut().poll(&mut cx)) {
+                        return r;
+                    }
+                } else {
+                    let task = self.ready.swap_remove(i);
+                    self.worker.run_task(task);
+                }
+
+                if self.rng.chance(IO_PROBABILITY) {
+                    self.worker.poll_events(Some(Duration::ZERO));
+                }
+                continue;
+            }
+
+            // All idle, dispatch the IO events then fire the next timers.
+            self.worker.poll_events(Some(Duration::ZERO));
+            if self.has_ready(&notified) {
+                continue;
+            }
+
+            if let Some(deadline) = inner.timers.next_deadline() {
+                inner.timers.advance_to(deadline);
+                let mut expired = inner.timers.expired();
+                self.rng.shuffle(&mut expired);
+                expired.into_iter().for_each(task::Waker::wake);
+                continue;
+            }
+
+            if inner.drivers[0].metrics().source_count() == 0 {
+                panic!("the simulation is deadlocked, all tasks are idle with no timer pending");
+            }
+            // Only the IO sources can make progress.
+            self.worker.poll_events(None);
+        }
+    }
+
+    fn has_ready(&self, notified: &Notified) -> bool {
+        notified.notified.load(Ordering::SeqCst)
+            || self.handle.inner.shared.global_queue_depth() > 0
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.handle.inner.shutdown();
+        self.ready.clear();
+    }
+}
+
+/// Waker for the future passed to `block_on`.
+struct Notified {
+    handle: scheduler::handle::Handle,
+    notified: AtomicBool,
+}
+
+impl Wake for Notified {
+    fn wake(self: Arc<Self>) {
+        self.notified.store(true, Ordering::SeqCst);
+        // Woken from another thread while blocking on the IO sources.
+        self.handle.drivers[0].unpark();
+    }
+}
+
+/// Print the seed if the simulation panics.
+struct ReportSeed(u64);
+
+impl Drop for ReportSeed {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            eprintln!(
+                "dirtio: simulation failed with seed {}, replay it with {}={}",
+                self.0, SEED_ENV, self.0
+            );
+        }
+    }
+}
+
+fn random_seed() -> u64 {
+    RandomState::new().hash_one(Instant::now())
+}
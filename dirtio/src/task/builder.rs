@@ -0,0 +1,47 @@
+use super::JoinHandle;
+
+use crate::runtime::{context, Handle};
+
+use futures::Future;
+
+/// Configure a task before spawning it.
+#[derive(Debug, Default)]
+pub struct Builder {
+    name: Option<String>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the task, shown in the task metadata.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Spawn the task onto current runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the context of a runtime.
+    #[track_caller]
+    pub fn spawn<F>(self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        context::current().spawn_named(fut, self.name)
+    }
+
+    /// Spawn the task onto the runtime of the handle.
+    #[track_caller]
+    pub fn spawn_on<F>(self, fut: F, handle: &Handle) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        handle.inner.spawn_named(fut, self.name)
+    }
+}
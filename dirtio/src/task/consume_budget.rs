@@ -0,0 +1,21 @@
+use crate::runtime::{coop, trace};
+
+use std::task::Poll;
+
+use futures::future::poll_fn;
+
+/// Consume a unit of the budget of current task, yields if it's used up.
+///
+/// Use this in a loop doing no IO through dirtio, so that the task doesn't
+/// starve the others on the same worker.
+pub async fn consume_budget() {
+    poll_fn(|cx| {
+        let Poll::Ready(mut coop) = coop::poll_proceed(cx) else {
+            trace::leaf("consume_budget");
+            return Poll::Pending;
+        };
+        coop.made_progress();
+        Poll::Ready(())
+    })
+    .await
+}
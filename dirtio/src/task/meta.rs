@@ -0,0 +1,26 @@
+use super::Id;
+
+use std::panic::Location;
+
+/// Metadata of a task, passed to the task hooks of the runtime.
+#[derive(Clone, Copy, Debug)]
+pub struct TaskMeta<'a> {
+    pub(crate) id: Id,
+    pub(crate) name: Option<&'a str>,
+    pub(crate) location: &'static Location<'static>,
+}
+
+impl<'a> TaskMeta<'a> {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    /// Where the task was spawned.
+    pub fn spawned_at(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
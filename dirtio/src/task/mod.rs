@@ -0,0 +1,35 @@
+mod builder;
+pub use builder::Builder;
+
+mod consume_budget;
+pub use consume_budget::consume_budget;
+
+mod id;
+pub use id::Id;
+
+mod meta;
+pub use meta::TaskMeta;
+
+mod traced;
+pub use traced::{traced, Traced};
+
+mod yield_now;
+pub use yield_now::yield_now;
+
+pub use crate::runtime::scheduler::join_handle::{JoinError, JoinHandle};
+
+use crate::runtime::context;
+
+/// Returns the id of current task.
+///
+/// # Panics
+///
+/// Panics if called outside of a task.
+pub fn id() -> Id {
+    try_id().expect("called outside of a task")
+}
+
+/// Returns the id of current task, `None` if called outside of a task.
+pub fn try_id() -> Option<Id> {
+    context::current_task()
+}
@@ -0,0 +1,41 @@
+use crate::runtime::trace;
+
+use std::panic::Location;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Future;
+
+/// Make the future an await point in the traces of `Handle::dump`, at the
+/// location of the caller.
+#[track_caller]
+pub fn traced<F>(fut: F) -> Traced<F>
+where
+    F: Future,
+{
+    Traced {
+        inner: fut,
+        location: Location::caller(),
+    }
+}
+
+/// Future returned by `traced`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Traced<F> {
+    inner: F,
+    location: &'static Location<'static>,
+}
+
+impl<F> Future for Traced<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let location = self.location;
+        // Safety: `inner` is never moved out of the pinned `Traced`.
+        let inner = unsafe { self.map_unchecked_mut(|this| &mut this.inner) };
+        trace::frame(location, || inner.poll(cx))
+    }
+}
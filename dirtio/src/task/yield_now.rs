@@ -0,0 +1,30 @@
+use crate::runtime::trace;
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Future;
+
+/// Yield to the other tasks, current task is rescheduled right away.
+pub async fn yield_now() {
+    YieldNow { yielded: false }.await
+}
+
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        trace::leaf("yield_now");
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
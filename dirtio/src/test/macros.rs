@@ -0,0 +1,76 @@
+/// Assert that a `Poll` is `Ready`, returns its value.
+#[macro_export]
+macro_rules! assert_ready {
+    ($e:expr) => {
+        match $e {
+            ::core::task::Poll::Ready(v) => v,
+            ::core::task::Poll::Pending => panic!("pending"),
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        match $e {
+            ::core::task::Poll::Ready(v) => v,
+            ::core::task::Poll::Pending => panic!("pending; {}", format_args!($($msg)+)),
+        }
+    };
+}
+
+/// Assert that a `Poll` is `Ready(Ok(_))`, returns the value.
+#[macro_export]
+macro_rules! assert_ready_ok {
+    ($e:expr) => {
+        match $crate::assert_ready!($e) {
+            Ok(v) => v,
+            Err(e) => panic!("error = {:?}", e),
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        match $crate::assert_ready!($e, $($msg)+) {
+            Ok(v) => v,
+            Err(e) => panic!("error = {:?}; {}", e, format_args!($($msg)+)),
+        }
+    };
+}
+
+/// Assert that a `Poll` is `Ready(Err(_))`, returns the error.
+#[macro_export]
+macro_rules! assert_ready_err {
+    ($e:expr) => {
+        match $crate::assert_ready!($e) {
+            Ok(v) => panic!("ok = {:?}", v),
+            Err(e) => e,
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        match $crate::assert_ready!($e, $($msg)+) {
+            Ok(v) => panic!("ok = {:?}; {}", v, format_args!($($msg)+)),
+            Err(e) => e,
+        }
+    };
+}
+
+/// Assert that a `Poll` is `Ready` with a value equal to `expected`.
+#[macro_export]
+macro_rules! assert_ready_eq {
+    ($e:expr, $expected:expr) => {
+        assert_eq!($crate::assert_ready!($e), $expected)
+    };
+    ($e:expr, $expected:expr, $($msg:tt)+) => {
+        assert_eq!($crate::assert_ready!($e, $($msg)+), $expected, $($msg)+)
+    };
+}
+
+/// Assert that a `Poll` is `Pending`.
+#[macro_export]
+macro_rules! assert_pending {
+    ($e:expr) => {
+        if let ::core::task::Poll::Ready(v) = $e {
+            panic!("ready; value = {:?}", v);
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        if let ::core::task::Poll::Ready(v) = $e {
+            panic!("ready; value = {:?}; {}", v, format_args!($($msg)+));
+        }
+    };
+}
@@ -0,0 +1,14 @@
+//! Utilities to test futures by hand, with the `test-util` feature.
+//!
+//! `spawn` wraps a future or a stream in a mock task, which polls it with
+//! a waker recording the wakeups, so a test checks that the value is woken
+//! when it becomes ready. The results of the polls are checked with
+//! `assert_pending!` and the `assert_ready!` family of macros.
+//!
+//! Tasks spawned on a current thread runtime are run by hand with
+//! `Runtime::run_until_stalled`.
+
+mod macros;
+
+mod task;
+pub use task::{spawn, Spawn};
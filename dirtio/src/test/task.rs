@@ -0,0 +1,102 @@
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures::{Future, Stream};
+
+/// Wrap `task` in a mock task, to poll it by hand.
+pub fn spawn<T>(task: T) -> Spawn<T> {
+    Spawn {
+        task: Box::pin(task),
+        waker: Arc::new(MockWaker::default()),
+    }
+}
+
+/// A future or a stream polled by hand, returned by `spawn`.
+pub struct Spawn<T> {
+    task: Pin<Box<T>>,
+    waker: Arc<MockWaker>,
+}
+
+#[derive(Default)]
+struct MockWaker {
+    /// Set when woken, cleared by a poll.
+    woken: AtomicBool,
+    wakes: AtomicUsize,
+}
+
+impl Wake for MockWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<T> Spawn<T> {
+    /// Returns `true` if the task was woken since the last poll.
+    pub fn is_woken(&self) -> bool {
+        self.waker.woken.load(Ordering::SeqCst)
+    }
+
+    /// Number of times the task was woken.
+    pub fn wake_count(&self) -> usize {
+        self.waker.wakes.load(Ordering::SeqCst)
+    }
+
+    /// Number of the wakers of the task held by the polled value, to check
+    /// that it drops them.
+    pub fn waker_ref_count(&self) -> usize {
+        Arc::strong_count(&self.waker) - 1
+    }
+
+    /// Call `f` with the context of the task, to poll the value with a
+    /// function other than `poll` and `poll_next`.
+    pub fn enter<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Context<'_>, Pin<&mut T>) -> R,
+    {
+        self.waker.woken.store(false, Ordering::SeqCst);
+        let waker = Waker::from(self.waker.clone());
+        let mut cx = Context::from_waker(&waker);
+        f(&mut cx, self.task.as_mut())
+    }
+
+    pub fn into_inner(self) -> T
+    where
+        T: Unpin,
+    {
+        *Pin::into_inner(self.task)
+    }
+}
+
+impl<T: Future> Spawn<T> {
+    pub fn poll(&mut self) -> Poll<T::Output> {
+        self.enter(|cx, task| task.poll(cx))
+    }
+}
+
+impl<T: Stream> Spawn<T> {
+    pub fn poll_next(&mut self) -> Poll<Option<T::Item>> {
+        self.enter(|cx, task| task.poll_next(cx))
+    }
+}
+
+impl<T> Deref for Spawn<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.task
+    }
+}
+
+impl<T: Unpin> DerefMut for Spawn<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.task
+    }
+}
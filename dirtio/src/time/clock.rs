@@ -0,0 +1,41 @@
+use crate::runtime::scheduler::handle::Handle;
+
+use std::time::Duration;
+
+/// Pause the time of the current runtime, it then only moves forward with
+/// `advance`, or to the next timer when every task is idle.
+///
+/// # Panics
+///
+/// Panics if called outside of a current thread runtime, or if the time is
+/// already paused.
+pub fn pause() {
+    Handle::current().timers.pause();
+}
+
+/// Resume the paused time, it continues from where it was paused.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime, in a simulation, or if the time is
+/// not paused.
+pub fn resume() {
+    let handle = Handle::current();
+    #[cfg(feature = "sim")]
+    assert!(
+        !handle.config.simulation,
+        "time can't be resumed in a simulation"
+    );
+    handle.timers.resume();
+}
+
+/// Advance the paused time by `duration`, firing the timers expired
+/// meanwhile, then yield so that the woken tasks get to run.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime, or if the time is not paused.
+pub async fn advance(duration: Duration) {
+    Handle::current().timers.advance(duration);
+    crate::task::yield_now().await;
+}
@@ -0,0 +1,19 @@
+//! Timers driven by the runtime.
+//!
+//! The time is read from the clock of current runtime, which is virtual
+//! in a simulation. With the `test-util` feature, the time of a current
+//! thread runtime can be paused and advanced by hand.
+
+#[cfg(feature = "test-util")]
+mod clock;
+#[cfg(feature = "test-util")]
+pub use clock::{advance, pause, resume};
+
+mod instant;
+pub use instant::Instant;
+
+mod sleep;
+pub use sleep::{sleep, sleep_until, Sleep};
+
+mod timeout;
+pub use timeout::{timeout, timeout_at, Elapsed, Timeout};
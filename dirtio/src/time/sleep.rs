@@ -0,0 +1,94 @@
+use super::Instant;
+
+use crate::runtime::scheduler::handle::Handle;
+use crate::runtime::time::TimerKey;
+use crate::runtime::trace;
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Future;
+
+/// Wait until `duration` has elapsed.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+pub fn sleep(duration: Duration) -> Sleep {
+    let handle = Handle::current();
+    let now = Instant::from_std(handle.timers.now());
+    Sleep::new(handle, now + duration)
+}
+
+/// Wait until `deadline` is reached.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep::new(Handle::current(), deadline)
+}
+
+/// Future returned by `sleep` and `sleep_until`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Sleep {
+    handle: Handle,
+    deadline: Instant,
+    /// Registered timer, after the first pending poll.
+    key: Option<TimerKey>,
+}
+
+impl Sleep {
+    fn new(handle: Handle, deadline: Instant) -> Self {
+        Self {
+            handle,
+            deadline,
+            key: None,
+        }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns `true` if the deadline has been reached.
+    pub fn is_elapsed(&self) -> bool {
+        self.handle.timers.now() >= self.deadline.into_std()
+    }
+
+    /// Wait until the new `deadline` instead.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.unregister();
+        self.deadline = deadline;
+    }
+
+    fn unregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.handle.timers.remove(key);
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.is_elapsed() {
+            self.unregister();
+            return Poll::Ready(());
+        }
+
+        let deadline = self.deadline.into_std();
+        let key = self.handle.register_timer(self.key, deadline, cx.waker());
+        self.key = Some(key);
+        trace::leaf("sleep");
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
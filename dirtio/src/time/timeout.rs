@@ -0,0 +1,91 @@
+use super::{sleep, sleep_until, Instant, Sleep};
+
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Future;
+
+/// Run the future, failing with `Elapsed` if it doesn't complete within
+/// `duration`.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+pub fn timeout<F>(duration: Duration, fut: F) -> Timeout<F>
+where
+    F: Future,
+{
+    Timeout {
+        inner: fut,
+        sleep: sleep(duration),
+    }
+}
+
+/// Run the future, failing with `Elapsed` if it doesn't complete before
+/// `deadline`.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+pub fn timeout_at<F>(deadline: Instant, fut: F) -> Timeout<F>
+where
+    F: Future,
+{
+    Timeout {
+        inner: fut,
+        sleep: sleep_until(deadline),
+    }
+}
+
+/// Future returned by `timeout` and `timeout_at`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Timeout<F> {
+    inner: F,
+    sleep: Sleep,
+}
+
+impl<F> Timeout<F> {
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> Future for Timeout<F>
+where
+    F: Future,
+{
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `inner` is never moved out of the pinned `Timeout`, and
+        // `sleep` is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+
+        if let Poll::Ready(output) = inner.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep)
+            .poll(cx)
+            .map(|()| Err(Elapsed(())))
+    }
+}
+
+/// Error returned by `Timeout` when the deadline is reached first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use dirtio::net::tcp::TcpListener;
+use dirtio::runtime::Builder;
+
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("dirtio-{}-{}.sock", name, std::process::id()))
+}
+
+/// Read the first snapshot sent by the console.
+fn read_snapshot(path: &PathBuf) -> Vec<String> {
+    let stream = UnixStream::connect(path).unwrap();
+    BufReader::new(stream)
+        .lines()
+        .map(Result::unwrap)
+        .take_while(|line| line != "end")
+        .collect()
+}
+
+#[test]
+fn console_reports_resources() {
+    let path = socket_path("resources");
+    let rt = Builder::new_current_thread()
+        .enable_console(&path)
+        .build()
+        .unwrap();
+    let _listener =
+        rt.block_on(async { TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap() });
+
+    let snapshot = read_snapshot(&path);
+    assert!(
+        snapshot
+            .iter()
+            .any(|line| line.starts_with("resource\t") && line.contains("TcpListener")),
+        "{:?}",
+        snapshot
+    );
+    drop(rt);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn console_replaces_stale_socket() {
+    let path = socket_path("stale");
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let rt = Builder::new_current_thread()
+        .enable_console(&path)
+        .build()
+        .unwrap();
+    assert!(read_snapshot(&path)[0].starts_with("snapshot\t"));
+    drop(rt);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn console_keeps_other_files() {
+    let path = socket_path("file");
+    fs::write(&path, "data").unwrap();
+
+    let err = Builder::new_current_thread()
+        .enable_console(&path)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "data");
+    fs::remove_file(&path).unwrap();
+}
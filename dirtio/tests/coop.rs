@@ -0,0 +1,79 @@
+use dirtio::net::udp::UdpSocket;
+use dirtio::runtime::Builder;
+use dirtio::task::{consume_budget, yield_now};
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+
+use futures::future::poll_fn;
+
+#[test]
+fn consume_budget_yields_once_used_up() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let handle = rt.handle().spawn(async {
+        // Count the units consumed in a single poll of the task.
+        let units = poll_fn(|cx| {
+            let mut units = 0;
+            loop {
+                match pin!(consume_budget()).poll(cx) {
+                    Poll::Ready(()) => units += 1,
+                    Poll::Pending => return Poll::Ready(units),
+                }
+            }
+        })
+        .await;
+        // Woken up right away, with a fresh budget.
+        consume_budget().await;
+        units
+    });
+    assert_eq!(rt.block_on(handle).unwrap(), 128);
+}
+
+#[test]
+fn consume_budget_lets_others_run() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        let busy = dirtio::spawn({
+            let stop = stop.clone();
+            async move {
+                while !stop.load(Ordering::SeqCst) {
+                    consume_budget().await;
+                }
+            }
+        });
+        yield_now().await;
+        // Only reached if the busy task yields.
+        stop.store(true, Ordering::SeqCst);
+        busy.await.unwrap();
+    });
+}
+
+#[test]
+fn always_ready_io_lets_others_run() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        let busy = dirtio::spawn({
+            let stop = stop.clone();
+            async move {
+                let socket = UdpSocket::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+                let target = socket.local_addr().unwrap();
+                let mut sent = 0;
+                // Always writable, the datagrams overflowing the buffer
+                // are dropped.
+                while !stop.load(Ordering::SeqCst) {
+                    let _ = socket.send_to(b"x", target).await;
+                    sent += 1;
+                }
+                sent
+            }
+        });
+        yield_now().await;
+        stop.store(true, Ordering::SeqCst);
+        assert!(busy.await.unwrap() > 0);
+    });
+}
@@ -0,0 +1,68 @@
+use dirtio::runtime::{Builder, TaskState};
+use dirtio::task::traced;
+use dirtio::time::sleep;
+
+use std::time::Duration;
+
+#[test]
+fn dump_is_empty_unless_enabled() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let _handle = rt
+        .handle()
+        .spawn(async { sleep(Duration::from_secs(3600)).await });
+    rt.block_on(async { sleep(Duration::from_millis(10)).await });
+
+    assert!(rt.handle().dump().tasks().is_empty());
+    // Counted all the same.
+    assert_eq!(rt.metrics().num_alive_tasks(), 1);
+}
+
+#[test]
+fn dump_lists_idle_tasks_with_their_await_points() {
+    let rt = Builder::new_current_thread()
+        .enable_task_dump()
+        .build()
+        .unwrap();
+    let _handle = rt
+        .handle()
+        .spawn(traced(async { sleep(Duration::from_secs(3600)).await }));
+    rt.block_on(async { sleep(Duration::from_millis(10)).await });
+
+    let dump = rt.handle().dump();
+    let [task] = dump.tasks() else {
+        panic!("expected a single task: {}", dump);
+    };
+    assert_eq!(task.state(), TaskState::Idle);
+    assert_eq!(task.polls(), 1);
+    let trace = task.trace().to_string();
+    assert!(trace.contains("tests/dump.rs"), "{}", trace);
+    assert!(trace.contains("pending on sleep"), "{}", trace);
+    assert_eq!(rt.metrics().num_alive_tasks(), 1);
+}
+
+#[test]
+fn dropped_tasks_leave_the_dump() {
+    let rt = Builder::new_current_thread()
+        .enable_task_dump()
+        .build()
+        .unwrap();
+    let handle = rt.handle().spawn(async {});
+    rt.block_on(handle).unwrap();
+
+    assert!(rt.handle().dump().tasks().is_empty());
+    assert_eq!(rt.metrics().num_alive_tasks(), 0);
+}
+
+#[test]
+fn idle_task_warning_enables_dump() {
+    let rt = Builder::new_current_thread()
+        .warn_on_idle_task(Duration::from_secs(3600))
+        .build()
+        .unwrap();
+    let _handle = rt
+        .handle()
+        .spawn(async { sleep(Duration::from_secs(3600)).await });
+    rt.block_on(async { sleep(Duration::from_millis(10)).await });
+
+    assert_eq!(rt.handle().dump().tasks().len(), 1);
+}
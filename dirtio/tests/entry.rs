@@ -0,0 +1,33 @@
+use dirtio::runtime::Handle;
+
+use std::num::ParseIntError;
+
+#[dirtio::test]
+async fn test_returns_result() -> Result<(), ParseIntError> {
+    let n: u32 = "5".parse()?;
+    assert_eq!(n, 5);
+    Ok(())
+}
+
+#[dirtio::test]
+async fn test_runs_on_current_thread() {
+    assert_eq!(Handle::current().metrics().num_workers(), 1);
+}
+
+#[dirtio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_runs_on_multi_thread() {
+    assert_eq!(Handle::current().metrics().num_workers(), 2);
+}
+
+// Builds without the `test-util` feature.
+#[dirtio::test(start_paused = false)]
+async fn test_not_paused() {}
+
+mod reexport {
+    pub use dirtio as rt;
+}
+
+#[dirtio::test(crate = "reexport::rt")]
+async fn test_with_crate_path() {
+    assert_eq!(reexport::rt::spawn(async { 1 }).await.unwrap(), 1);
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "test-util")]
+
+use dirtio::net::tcp::TcpListener;
+use dirtio::runtime::{Builder, IoFaults};
+use dirtio::task::yield_now;
+use dirtio::time::{timeout, Instant};
+
+use std::io::Write;
+use std::pin::pin;
+use std::time::Duration;
+
+use futures::{poll, AsyncReadExt};
+
+/// Read a message sent in full beforehand, returns the length of each
+/// read down to the end of stream.
+fn read_lengths(faults: IoFaults) -> Vec<usize> {
+    let rt = Builder::new_current_thread()
+        .inject_io_faults(faults)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(&[1; 1000]).unwrap();
+        drop(client);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut lengths = Vec::new();
+        let mut buf = [0; 64];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            lengths.push(n);
+            if n == 0 {
+                return lengths;
+            }
+        }
+    })
+}
+
+fn faults(seed: u64) -> IoFaults {
+    IoFaults::new(seed).would_block(0.3).short_io(0.5)
+}
+
+#[test]
+fn seed_replays_the_faults() {
+    let lengths = read_lengths(faults(7));
+    assert!(lengths.iter().any(|&n| n > 0 && n < 64), "{:?}", lengths);
+    assert_eq!(read_lengths(faults(7)), lengths);
+    assert!((8..16).any(|seed| read_lengths(faults(seed)) != lengths));
+}
+
+#[test]
+fn short_reads_never_return_zero() {
+    for seed in 0..10 {
+        let lengths = read_lengths(IoFaults::new(seed).short_io(1.0));
+        let (eof, data) = lengths.split_last().unwrap();
+        assert_eq!(*eof, 0);
+        assert!(data.iter().all(|&n| n > 0), "{:?}", lengths);
+        assert_eq!(data.iter().sum::<usize>(), 1000);
+    }
+}
+
+#[test]
+fn delayed_operations_run_once_the_delay_is_over() {
+    let rt = Builder::new_current_thread()
+        .start_paused(true)
+        .inject_io_faults(IoFaults::new(3).delay(1.0, Duration::from_millis(100)))
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let start = Instant::now();
+        let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(&[1; 10]).unwrap();
+        drop(client);
+
+        // Each operation is delayed once, then runs.
+        let read = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            buf.len()
+        };
+        let len = timeout(Duration::from_secs(10), read).await.unwrap();
+        assert_eq!(len, 10);
+        assert!(start.elapsed() > Duration::ZERO);
+    });
+}
+
+#[test]
+fn dropped_socket_cancels_its_delay() {
+    let rt = Builder::new_current_thread()
+        .start_paused(true)
+        .inject_io_faults(IoFaults::new(5).delay(1.0, Duration::from_secs(3600)))
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"ping").unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(rt.next_timer_deadline(), None);
+
+        // Delayed once readable.
+        let mut buf = [0; 4];
+        let mut read = pin!(stream.read(&mut buf));
+        while rt.next_timer_deadline().is_none() {
+            assert!(poll!(read.as_mut()).is_pending());
+            yield_now().await;
+        }
+        drop(stream);
+        assert_eq!(rt.next_timer_deadline(), None);
+    });
+}
@@ -0,0 +1,83 @@
+use dirtio::runtime::{Builder, Handle, Runtime};
+
+use std::thread;
+
+fn multi_thread(workers: usize) -> Runtime {
+    Builder::new_multi_thread()
+        .worker_threads(workers)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn try_current_outside_a_runtime() {
+    let err = Handle::try_current().unwrap_err();
+    assert!(err.is_missing_context());
+    assert!(!err.is_thread_local_destroyed());
+    assert_eq!(
+        err.to_string(),
+        "called outside of the context of a dirtio runtime"
+    );
+}
+
+#[test]
+fn try_current_inside_a_runtime() {
+    let rt = multi_thread(2);
+    rt.block_on(async {
+        let handle = Handle::try_current().unwrap();
+        assert_eq!(handle.metrics().num_workers(), 2);
+    });
+
+    // On the worker threads.
+    let handle = rt.handle().spawn(async { Handle::try_current().is_ok() });
+    assert!(rt.block_on(handle).unwrap());
+
+    let current = Builder::new_current_thread().build().unwrap();
+    current.block_on(async { Handle::try_current().unwrap() });
+    assert!(Handle::try_current().is_err());
+}
+
+#[test]
+fn try_current_on_another_thread() {
+    let rt = multi_thread(1);
+    let _guard = rt.enter();
+    assert!(Handle::try_current().is_ok());
+    let other = thread::spawn(|| Handle::try_current().is_err());
+    assert!(other.join().unwrap());
+}
+
+#[test]
+fn enter_guard_restores_the_previous_context() {
+    let outer = multi_thread(1);
+    let inner = multi_thread(3);
+    let num_workers = || Handle::current().metrics().num_workers();
+
+    let outer_guard = outer.enter();
+    assert_eq!(num_workers(), 1);
+    {
+        let _inner_guard = inner.handle().enter();
+        assert_eq!(num_workers(), 3);
+    }
+    assert_eq!(num_workers(), 1);
+    drop(outer_guard);
+    assert!(Handle::try_current().unwrap_err().is_missing_context());
+}
+
+#[test]
+#[should_panic(expected = "cannot block on a runtime from within a runtime")]
+fn nested_block_on_panics() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let other = multi_thread(1);
+    rt.block_on(async { other.block_on(async {}) });
+}
+
+#[test]
+fn nested_handle_block_on_panics() {
+    let rt = multi_thread(1);
+    let handle = rt.handle().spawn(async {
+        let err = std::panic::catch_unwind(|| Handle::current().block_on(async {})).unwrap_err();
+        err.downcast::<&str>().unwrap().to_string()
+    });
+    let msg = rt.block_on(handle).unwrap();
+    assert!(msg.starts_with("cannot block on a runtime from within a runtime"));
+}
@@ -0,0 +1,154 @@
+use dirtio::time::sleep;
+
+use std::future::pending;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Sets the flag when dropped, along with the future holding it.
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[dirtio::test]
+async fn select_skips_disabled_branches() {
+    let v = dirtio::select! {
+        v = async { 1 }, if false => v,
+        v = async { 2 } => v,
+    };
+    assert_eq!(v, 2);
+}
+
+#[dirtio::test]
+async fn select_polls_in_order_when_biased() {
+    for _ in 0..100 {
+        let v = dirtio::select! {
+            biased;
+            v = async { 1 } => v,
+            v = async { 2 } => v,
+        };
+        assert_eq!(v, 1);
+    }
+}
+
+#[dirtio::test]
+async fn select_polls_from_a_random_branch() {
+    let mut seen = [false; 2];
+    for _ in 0..100 {
+        let v = dirtio::select! {
+            v = async { 0 } => v,
+            v = async { 1 } => v,
+        };
+        seen[v] = true;
+    }
+    assert_eq!(seen, [true, true]);
+}
+
+#[dirtio::test]
+async fn select_runs_else_once_all_disabled() {
+    let v = dirtio::select! {
+        v = async { 1 }, if false => v,
+        else => 0,
+    };
+    assert_eq!(v, 0);
+
+    let v = dirtio::select! {
+        Some(v) = async { None::<i32> } => v,
+        else => -1,
+    };
+    assert_eq!(v, -1);
+}
+
+#[dirtio::test]
+#[should_panic(expected = "all branches are disabled and there is no else branch")]
+async fn select_panics_without_else() {
+    dirtio::select! {
+        v = async { 1 }, if false => v,
+    };
+}
+
+#[dirtio::test]
+async fn select_disables_mismatched_branch() {
+    let v = dirtio::select! {
+        Some(v) = async { None::<i32> } => v,
+        v = async {
+            sleep(Duration::from_millis(10)).await;
+            2
+        } => v,
+    };
+    assert_eq!(v, 2);
+}
+
+#[dirtio::test]
+async fn select_drops_losers_before_handler() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = SetOnDrop(dropped.clone());
+    dirtio::select! {
+        _ = async {} => {
+            assert!(dropped.load(Ordering::SeqCst));
+        }
+        _ = async move {
+            let _guard = guard;
+            pending::<()>().await;
+        } => unreachable!(),
+    }
+}
+
+/// Select over one branch for each value, only the last one enabled.
+macro_rules! select_last {
+    ($($i:literal)*) => {
+        dirtio::select! {
+            $(v = async { $i }, if $i == 63 => v,)*
+        }
+    };
+}
+
+#[dirtio::test]
+async fn select_supports_64_branches() {
+    let v = select_last!(
+        0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+        32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60
+        61 62 63
+    );
+    assert_eq!(v, 63);
+}
+
+#[dirtio::test]
+async fn join_returns_all_outputs() {
+    let res = dirtio::join!(
+        async {
+            sleep(Duration::from_millis(10)).await;
+            1
+        },
+        async { "two" },
+    );
+    assert_eq!(res, (1, "two"));
+}
+
+#[dirtio::test]
+async fn try_join_returns_all_values() {
+    let res: Result<_, ()> = dirtio::try_join!(async { Ok(1) }, async { Ok("two") });
+    assert_eq!(res, Ok((1, "two")));
+}
+
+#[dirtio::test]
+async fn try_join_returns_first_error() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = SetOnDrop(dropped.clone());
+    let res = dirtio::try_join!(
+        async move {
+            let _guard = guard;
+            pending::<Result<(), &str>>().await
+        },
+        async {
+            sleep(Duration::from_millis(10)).await;
+            Err::<(), _>("bad")
+        },
+    );
+    assert_eq!(res, Err("bad"));
+    assert!(dropped.load(Ordering::SeqCst));
+}
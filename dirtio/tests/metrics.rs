@@ -0,0 +1,59 @@
+use dirtio::runtime::Builder;
+
+use std::thread;
+use std::time::Duration;
+
+const MS: Duration = Duration::from_millis(1);
+
+#[test]
+fn poll_times_fall_into_their_buckets() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_poll_time_histogram()
+        .poll_time_histogram_resolution(50 * MS)
+        .poll_time_histogram_buckets(4)
+        .build()
+        .unwrap();
+    let metrics = rt.metrics();
+    assert!(metrics.poll_time_histogram_enabled());
+    assert_eq!(metrics.poll_time_histogram_num_buckets(), 4);
+    let ranges: Vec<_> = (0..4)
+        .map(|bucket| metrics.poll_time_histogram_bucket_range(bucket))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            Duration::ZERO..50 * MS,
+            50 * MS..100 * MS,
+            100 * MS..200 * MS,
+            200 * MS..Duration::MAX,
+        ]
+    );
+
+    // Block the worker for known durations, one poll each.
+    for blocked in [Duration::ZERO, 55 * MS, 210 * MS] {
+        let handle = rt.handle().spawn(async move { thread::sleep(blocked) });
+        rt.block_on(handle).unwrap();
+    }
+
+    let counts: Vec<_> = (0..4)
+        .map(|bucket| metrics.worker_poll_time_histogram_bucket_count(0, bucket))
+        .collect();
+    assert_eq!(counts, [1, 1, 0, 1]);
+    assert_eq!(metrics.worker_poll_count(0), 3);
+    assert!(metrics.worker_total_busy_duration(0) >= 265 * MS);
+    assert!(metrics.worker_park_count(0) > 0);
+    assert_eq!(metrics.worker_steal_count(0), 0);
+}
+
+#[test]
+fn histogram_is_disabled_by_default() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let metrics = rt.metrics();
+    assert!(!metrics.poll_time_histogram_enabled());
+    assert_eq!(metrics.poll_time_histogram_num_buckets(), 0);
+
+    let handle = rt.handle().spawn(async {});
+    rt.block_on(handle).unwrap();
+    assert_eq!(metrics.worker_poll_count(0), 1);
+}
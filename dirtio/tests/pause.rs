@@ -0,0 +1,73 @@
+#![cfg(feature = "test-util")]
+
+use dirtio::time::{self, sleep, Instant};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[dirtio::test(start_paused = true)]
+async fn idle_runtime_advances_to_the_next_timer() {
+    let wall = std::time::Instant::now();
+    let start = Instant::now();
+    sleep(Duration::from_secs(3600)).await;
+    assert_eq!(start.elapsed(), Duration::from_secs(3600));
+    assert!(wall.elapsed() < Duration::from_secs(1));
+}
+
+#[dirtio::test(start_paused = true)]
+async fn paused_time_stands_still() {
+    let start = Instant::now();
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(start.elapsed(), Duration::ZERO);
+}
+
+#[dirtio::test(start_paused = true)]
+async fn advance_fires_timers() {
+    let fired = Arc::new(AtomicBool::new(false));
+    let _handle = dirtio::spawn({
+        let fired = fired.clone();
+        async move {
+            sleep(Duration::from_secs(10)).await;
+            fired.store(true, Ordering::SeqCst);
+        }
+    });
+    // Start the sleep.
+    dirtio::task::yield_now().await;
+
+    time::advance(Duration::from_secs(9)).await;
+    assert!(!fired.load(Ordering::SeqCst));
+    time::advance(Duration::from_secs(1)).await;
+    assert!(fired.load(Ordering::SeqCst));
+}
+
+#[dirtio::test(start_paused = true)]
+async fn resume_continues_from_the_paused_time() {
+    let start = Instant::now();
+    time::advance(Duration::from_secs(5)).await;
+    time::resume();
+    assert!(start.elapsed() >= Duration::from_secs(5));
+    assert!(start.elapsed() < Duration::from_secs(6));
+}
+
+#[dirtio::test(start_paused = true)]
+#[should_panic(expected = "time is not paused")]
+async fn resume_panics_if_not_paused() {
+    time::resume();
+    time::resume();
+}
+
+#[dirtio::test]
+#[should_panic(expected = "time is already paused")]
+async fn pause_panics_if_paused() {
+    time::pause();
+    time::pause();
+}
+
+#[cfg(feature = "sim")]
+#[test]
+#[should_panic(expected = "time can't be resumed in a simulation")]
+fn resume_panics_in_a_simulation() {
+    let mut rt = dirtio::sim::Runtime::new(1).unwrap();
+    rt.block_on(async { time::resume() });
+}
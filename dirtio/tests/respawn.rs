@@ -0,0 +1,93 @@
+#![cfg(target_os = "linux")]
+
+use dirtio::runtime::Builder;
+
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Set by the parent test to run the child test.
+const CHILD: &str = "DIRTIO_RESPAWN_CHILD";
+
+const RLIMIT_AS: i32 = 9;
+
+#[repr(C)]
+struct Rlimit {
+    cur: u64,
+    max: u64,
+}
+
+extern "C" {
+    fn getrlimit(resource: i32, rlim: *mut Rlimit) -> i32;
+    fn setrlimit(resource: i32, rlim: *const Rlimit) -> i32;
+}
+
+/// Limit the address space to `extra` bytes more than used now.
+fn limit_address_space(extra: u64) {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let size: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmSize:"))
+        .and_then(|size| size.trim().strip_suffix("kB"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+
+    let mut limit = Rlimit { cur: 0, max: 0 };
+    // Safety: the struct outlives the calls.
+    unsafe {
+        assert_eq!(getrlimit(RLIMIT_AS, &mut limit), 0);
+        limit.cur = size * 1024 + extra;
+        assert_eq!(setrlimit(RLIMIT_AS, &limit), 0);
+    }
+}
+
+#[test]
+fn failed_respawn_is_reported() {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["failed_respawn_child", "--exact", "--nocapture"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("dirtio: worker 0 panicked, failed to respawn it"),
+        "{}",
+        stderr
+    );
+}
+
+/// Kill the only worker with a panicking hook once its replacement can't
+/// get a stack.
+#[test]
+fn failed_respawn_child() {
+    if std::env::var_os(CHILD).is_none() {
+        return;
+    }
+
+    let limited = Arc::new(AtomicBool::new(false));
+    let rt = {
+        let limited = limited.clone();
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_stack_size(1 << 30)
+            .on_thread_park(move || {
+                if limited.swap(false, Ordering::SeqCst) {
+                    panic!("park hook panicked");
+                }
+            })
+            .build()
+            .unwrap()
+    };
+    thread::sleep(Duration::from_millis(50));
+
+    limit_address_space(64 << 20);
+    limited.store(true, Ordering::SeqCst);
+    let _handle = rt.handle().spawn(async {});
+    thread::sleep(Duration::from_millis(200));
+    // The runtime is left without workers.
+    std::mem::forget(rt);
+}
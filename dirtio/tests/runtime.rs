@@ -0,0 +1,205 @@
+use dirtio::runtime::{Builder, Runtime};
+use dirtio::time::sleep;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn sharded(workers: usize) -> Runtime {
+    Builder::new_multi_thread()
+        .worker_threads(workers)
+        .sharded_io(true)
+        .build()
+        .unwrap()
+}
+
+fn total_parks(rt: &Runtime) -> u64 {
+    let metrics = rt.metrics();
+    (0..metrics.num_workers())
+        .map(|worker| metrics.worker_park_count(worker))
+        .sum()
+}
+
+#[test]
+fn idle_sharded_workers_block() {
+    let rt = sharded(4);
+    thread::sleep(Duration::from_millis(50));
+
+    let parks = total_parks(&rt);
+    thread::sleep(Duration::from_millis(200));
+    // Each worker blocks once, instead of polling its driver in a loop.
+    assert!(total_parks(&rt) - parks <= 4);
+}
+
+#[test]
+fn sharded_workers_wake_up_for_tasks() {
+    let rt = sharded(4);
+    thread::sleep(Duration::from_millis(50));
+
+    for i in 0..10 {
+        let handle = rt.handle().spawn(async move { i * 2 });
+        assert_eq!(rt.block_on(handle).unwrap(), i * 2);
+    }
+}
+
+#[test]
+fn sharded_workers_wake_up_for_timers() {
+    let rt = sharded(2);
+    thread::sleep(Duration::from_millis(50));
+
+    // Registered from off the workers while they all block.
+    let start = Instant::now();
+    rt.block_on(async { sleep(Duration::from_millis(20)).await });
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+
+    let start = Instant::now();
+    let handle = rt.handle().spawn(async {
+        sleep(Duration::from_millis(20)).await;
+        sleep(Duration::from_millis(20)).await;
+    });
+    rt.block_on(handle).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn park_hooks_fire_around_blocking() {
+    let parks = Arc::new(AtomicUsize::new(0));
+    let unparks = Arc::new(AtomicUsize::new(0));
+    let rt = {
+        let parks = parks.clone();
+        let unparks = unparks.clone();
+        Builder::new_multi_thread()
+            .worker_threads(2)
+            .on_thread_park(move || {
+                parks.fetch_add(1, Ordering::SeqCst);
+            })
+            .on_thread_unpark(move || {
+                unparks.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap()
+    };
+    thread::sleep(Duration::from_millis(50));
+
+    let before = parks.load(Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(200));
+    assert!(parks.load(Ordering::SeqCst) - before <= 2);
+
+    let handle = rt.handle().spawn(async { 1 });
+    assert_eq!(rt.block_on(handle).unwrap(), 1);
+    assert!(parks.load(Ordering::SeqCst) > 0);
+
+    drop(rt);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(parks.load(Ordering::SeqCst), unparks.load(Ordering::SeqCst));
+}
+
+/// Wait for the value to reach `expected`, the worker threads stop
+/// after the runtime is dropped.
+fn wait_for(value: &AtomicUsize, expected: usize) {
+    let start = Instant::now();
+    while value.load(Ordering::SeqCst) != expected {
+        assert!(start.elapsed() < Duration::from_secs(5), "stalled");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn worker_threads_are_named() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .build()
+        .unwrap();
+    let handle = rt
+        .handle()
+        .spawn(async { thread::current().name().map(String::from) });
+    assert_eq!(rt.block_on(handle).unwrap().unwrap(), "dirtio-worker");
+
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("custom")
+        .build()
+        .unwrap();
+    let handle = rt
+        .handle()
+        .spawn(async { thread::current().name().map(String::from) });
+    assert_eq!(rt.block_on(handle).unwrap().unwrap(), "custom");
+}
+
+#[test]
+fn thread_name_fn_names_each_worker() {
+    let next = AtomicUsize::new(0);
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let rt = {
+        let names = names.clone();
+        Builder::new_multi_thread()
+            .worker_threads(3)
+            .thread_name_fn(move || format!("worker-{}", next.fetch_add(1, Ordering::SeqCst)))
+            .on_thread_start(move || {
+                let name = thread::current().name().unwrap().to_string();
+                names.lock().unwrap().push(name);
+            })
+            .build()
+            .unwrap()
+    };
+
+    let start = Instant::now();
+    while names.lock().unwrap().len() < 3 {
+        assert!(start.elapsed() < Duration::from_secs(5), "stalled");
+        thread::sleep(Duration::from_millis(1));
+    }
+    let mut names = names.lock().unwrap().clone();
+    names.sort();
+    assert_eq!(names, ["worker-0", "worker-1", "worker-2"]);
+    drop(rt);
+}
+
+#[test]
+fn start_and_stop_hooks_run_on_each_worker() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let rt = {
+        let started = started.clone();
+        let stopped = stopped.clone();
+        Builder::new_multi_thread()
+            .worker_threads(4)
+            .on_thread_start(move || {
+                started.fetch_add(1, Ordering::SeqCst);
+            })
+            .on_thread_stop(move || {
+                stopped.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap()
+    };
+    wait_for(&started, 4);
+    assert_eq!(stopped.load(Ordering::SeqCst), 0);
+
+    drop(rt);
+    wait_for(&stopped, 4);
+    assert_eq!(started.load(Ordering::SeqCst), 4);
+}
+
+/// Use about `depth` KiB of stack.
+fn use_stack(depth: usize) -> u8 {
+    let buf = std::hint::black_box([depth as u8; 1024]);
+    if depth == 0 {
+        return buf[0];
+    }
+    use_stack(depth - 1).wrapping_add(std::hint::black_box(buf)[1023])
+}
+
+#[test]
+fn worker_threads_get_the_stack_size() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_stack_size(64 * 1024 * 1024)
+        .build()
+        .unwrap();
+    // Overflows the 2 MiB default stack.
+    let handle = rt.handle().spawn(async { use_stack(4 * 1024) });
+    rt.block_on(handle).unwrap();
+}
@@ -0,0 +1,88 @@
+use dirtio::net::tcp::TcpListener;
+use dirtio::runtime::{Builder, Runtime, UnhandledPanic};
+use dirtio::time::sleep;
+
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+
+/// Spawn a task waiting on an IO source, and one waiting on a timer.
+fn spawn_idle(rt: &Runtime) -> Vec<dirtio::task::JoinHandle<()>> {
+    vec![
+        rt.handle().spawn(async {
+            let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            listener.accept().await.unwrap();
+        }),
+        rt.handle().spawn(async {
+            sleep(Duration::from_secs(3600)).await;
+        }),
+    ]
+}
+
+#[test]
+fn drop_cancels_idle_tasks() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    let handles = spawn_idle(&rt);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(rt.metrics().num_alive_tasks(), 2);
+
+    drop(rt);
+    for handle in handles {
+        let err = block_on(handle).unwrap_err();
+        assert!(err.is_cancelled(), "{:?}", err);
+    }
+}
+
+#[test]
+fn drop_cancels_idle_tasks_current_thread() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let handles = spawn_idle(&rt);
+    rt.block_on(async { sleep(Duration::from_millis(10)).await });
+
+    drop(rt);
+    for handle in handles {
+        let err = block_on(handle).unwrap_err();
+        assert!(err.is_cancelled(), "{:?}", err);
+    }
+}
+
+#[test]
+fn drop_cancels_queued_tasks() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let handle = rt.handle().spawn(async {});
+
+    drop(rt);
+    assert!(block_on(handle).unwrap_err().is_cancelled());
+}
+
+#[test]
+fn panic_is_returned_by_join_handle() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .build()
+        .unwrap();
+    let handle = rt.handle().spawn(async { panic!("boom") });
+
+    let err = rt.block_on(handle).unwrap_err();
+    assert!(err.is_panic());
+    assert_eq!(*err.into_panic().downcast::<&str>().unwrap(), "boom");
+
+    // The worker survives the panic.
+    assert_eq!(rt.block_on(rt.handle().spawn(async { 1 })).unwrap(), 1);
+}
+
+#[test]
+#[should_panic(expected = "a spawned task panicked")]
+fn shutdown_on_unhandled_panic() {
+    let rt = Builder::new_current_thread()
+        .unhandled_panic(UnhandledPanic::ShutdownRuntime)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let _ = dirtio::spawn(async { panic!("boom") }).await;
+    });
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "sim")]
+
+use dirtio::sim::Runtime;
+use dirtio::task::yield_now;
+use dirtio::time::sleep;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Run tasks yielding and sleeping together, returns the order they
+/// stepped in.
+fn interleaving(seed: u64) -> Vec<(usize, usize)> {
+    let mut rt = Runtime::new(seed).unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.block_on(async {
+        let mut handles = Vec::new();
+        for task in 0..4 {
+            let log = log.clone();
+            handles.push(dirtio::spawn(async move {
+                for step in 0..5 {
+                    log.lock().unwrap().push((task, step));
+                    yield_now().await;
+                }
+                // Expire together, fired in random order.
+                sleep(Duration::from_millis(10)).await;
+                log.lock().unwrap().push((task, 5));
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+    });
+    Arc::try_unwrap(log).unwrap().into_inner().unwrap()
+}
+
+#[test]
+fn seed_replays_the_interleaving() {
+    let first = interleaving(7);
+    assert_eq!(first.len(), 24);
+    assert_eq!(interleaving(7), first);
+}
+
+#[test]
+fn seeds_change_the_interleaving() {
+    let first = interleaving(0);
+    assert!((1..20).any(|seed| interleaving(seed) != first));
+}
+
+#[test]
+fn virtual_time_jumps_to_timers() {
+    let mut rt = Runtime::new(1).unwrap();
+    rt.block_on(async { sleep(Duration::from_secs(3600)).await });
+    assert_eq!(rt.elapsed(), Duration::from_secs(3600));
+}
+
+#[test]
+#[should_panic(expected = "the simulation is deadlocked")]
+fn deadlock_panics() {
+    let mut rt = Runtime::new(1).unwrap();
+    rt.block_on(futures::future::pending::<()>());
+}
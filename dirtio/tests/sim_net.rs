@@ -0,0 +1,345 @@
+#![cfg(feature = "sim")]
+
+use dirtio::net::tcp::{TcpListener, TcpStream};
+use dirtio::net::udp::UdpSocket;
+use dirtio::sim::net::{host, Network};
+use dirtio::sim::Runtime;
+use dirtio::task::yield_now;
+use dirtio::time::{sleep, timeout, Instant};
+
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Wake, Waker};
+use std::time::Duration;
+
+use futures::{AsyncReadExt, AsyncWriteExt};
+
+const SERVER: &str = "10.0.0.1";
+const CLIENT: &str = "10.0.0.2";
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+fn addr(ip: &str, port: u16) -> SocketAddr {
+    SocketAddr::new(ip.parse().unwrap(), port)
+}
+
+/// Connect a client on its own host to a server, returns both ends.
+async fn connect() -> (TcpStream, TcpStream) {
+    let mut listener = TcpListener::bind(addr(SERVER, 80)).unwrap();
+    let client = host(ip(CLIENT), async { TcpStream::connect(addr(SERVER, 80)) })
+        .await
+        .unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (client, server)
+}
+
+#[test]
+fn latency_delays_packets() {
+    let mut rt = Runtime::new(1).unwrap();
+    rt.network()
+        .set_latency(Duration::from_millis(10), Duration::from_millis(10));
+    rt.block_on(async {
+        let start = Instant::now();
+        let (mut client, mut server) = connect().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(10));
+
+        let start = Instant::now();
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        assert_eq!(start.elapsed(), Duration::from_millis(10));
+
+        let socket = UdpSocket::bind(addr(SERVER, 53)).unwrap();
+        let start = Instant::now();
+        host(ip(CLIENT), async {
+            let client = UdpSocket::bind(addr(CLIENT, 0)).unwrap();
+            client.send_to(b"query", addr(SERVER, 53)).await.unwrap();
+        })
+        .await;
+        let mut buf = [0; 16];
+        let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"query");
+        assert_eq!(from.ip(), ip(CLIENT));
+        assert_eq!(start.elapsed(), Duration::from_millis(10));
+    });
+}
+
+#[test]
+fn tcp_keeps_order_under_varying_latency() {
+    let mut rt = Runtime::new(2).unwrap();
+    rt.network()
+        .set_latency(Duration::from_millis(1), Duration::from_millis(50));
+    rt.block_on(async {
+        let (client, mut server) = connect().await;
+        for i in 0..20u8 {
+            client.write_owned(vec![i]).await.0.unwrap();
+        }
+        drop(client);
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, (0..20).collect::<Vec<u8>>());
+    });
+}
+
+/// Send `count` datagrams to a socket, returns the ones received in order.
+fn udp_burst(seed: u64, count: u8, loss: f64) -> Vec<u8> {
+    let mut rt = Runtime::new(seed).unwrap();
+    let network = rt.network();
+    network.set_latency(Duration::from_millis(1), Duration::from_millis(50));
+    network.set_loss(loss);
+    rt.block_on(async {
+        let socket = UdpSocket::bind(addr(SERVER, 53)).unwrap();
+        let client = UdpSocket::bind(addr(CLIENT, 0)).unwrap();
+        for i in 0..count {
+            client.send_to(&[i], addr(SERVER, 53)).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        let mut buf = [0; 1];
+        while let Ok(res) = timeout(Duration::from_secs(1), socket.recv(&mut buf)).await {
+            res.unwrap();
+            received.push(buf[0]);
+        }
+        received
+    })
+}
+
+#[test]
+fn udp_reorders_datagrams() {
+    let received = udp_burst(3, 50, 0.0);
+    let mut sorted = received.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..50).collect::<Vec<u8>>());
+    assert_ne!(received, sorted);
+}
+
+#[test]
+fn udp_loses_datagrams() {
+    let received = udp_burst(4, 100, 0.5);
+    assert!((10..90).contains(&received.len()), "{}", received.len());
+    // The same seed loses the same datagrams.
+    assert_eq!(udp_burst(4, 100, 0.5), received);
+}
+
+#[test]
+fn partition_stalls_tcp_until_repair() {
+    let mut rt = Runtime::new(5).unwrap();
+    rt.block_on(async {
+        let (mut client, mut server) = connect().await;
+        let network = Network::current();
+        network.partition(ip(SERVER), ip(CLIENT));
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        let res = timeout(Duration::from_secs(10), server.read_exact(&mut buf)).await;
+        assert!(res.is_err());
+
+        network.repair(ip(SERVER), ip(CLIENT));
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    });
+}
+
+/// Order in which readers on partitioned hosts get their data after the
+/// repair.
+fn repair_order(seed: u64) -> Vec<u8> {
+    let mut rt = Runtime::new(seed).unwrap();
+    rt.block_on(async {
+        let mut listener = TcpListener::bind(addr(SERVER, 80)).unwrap();
+        let network = Network::current();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut readers = Vec::new();
+        for i in 0..20u8 {
+            let client_ip = IpAddr::from([10, 0, 1, i]);
+            let mut client = host(client_ip, async { TcpStream::connect(addr(SERVER, 80)) })
+                .await
+                .unwrap();
+            let (mut server, _) = listener.accept().await.unwrap();
+            network.partition(ip(SERVER), client_ip);
+            client.write_all(&[i]).await.unwrap();
+
+            let order = order.clone();
+            readers.push(dirtio::spawn(async move {
+                let mut buf = [0; 1];
+                server.read_exact(&mut buf).await.unwrap();
+                order.lock().unwrap().push(buf[0]);
+            }));
+        }
+        sleep(Duration::from_secs(1)).await;
+        network.repair_all();
+        for reader in readers {
+            reader.await.unwrap();
+        }
+        let order = order.lock().unwrap().clone();
+        order
+    })
+}
+
+#[test]
+fn repair_replays_from_the_seed() {
+    let order = repair_order(6);
+    assert_eq!(order.len(), 20);
+    for _ in 0..5 {
+        assert_eq!(repair_order(6), order);
+    }
+}
+
+#[test]
+fn listener_drop_refuses_backlog() {
+    let mut rt = Runtime::new(6).unwrap();
+    rt.block_on(async {
+        let listener = TcpListener::bind(addr(SERVER, 80)).unwrap();
+        let mut client = TcpStream::connect(addr(SERVER, 80)).unwrap();
+        drop(listener);
+
+        let mut buf = [0; 1];
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+        let err = client.write_all(b"late").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+        let err = TcpStream::connect(addr(SERVER, 80)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    });
+}
+
+#[test]
+fn readers_share_a_udp_socket() {
+    for seed in 0..20 {
+        let mut rt = Runtime::new(seed).unwrap();
+        rt.network()
+            .set_latency(Duration::from_millis(1), Duration::from_millis(50));
+        rt.block_on(async {
+            let socket = Arc::new(UdpSocket::bind(addr(SERVER, 53)).unwrap());
+            let readers: Vec<_> = (0..2)
+                .map(|_| {
+                    let socket = socket.clone();
+                    dirtio::spawn(async move {
+                        let mut buf = [0; 1];
+                        socket.recv(&mut buf).await.unwrap();
+                    })
+                })
+                .collect();
+
+            let client = UdpSocket::bind(addr(CLIENT, 0)).unwrap();
+            for i in 0..2 {
+                client.send_to(&[i], addr(SERVER, 53)).await.unwrap();
+            }
+            for reader in readers {
+                reader.await.unwrap();
+            }
+        });
+    }
+}
+
+/// Wait for the task to make progress, returns it.
+async fn first_progress(progress: &AtomicUsize) -> usize {
+    loop {
+        match progress.load(Ordering::SeqCst) {
+            0 => yield_now().await,
+            n => return n,
+        }
+    }
+}
+
+#[test]
+fn busy_sockets_let_others_run() {
+    let mut rt = Runtime::new(7).unwrap();
+    rt.block_on(async {
+        let (client, mut server) = connect().await;
+
+        // Always writable, yields on the budget.
+        let written = Arc::new(AtomicUsize::new(0));
+        let writer = dirtio::spawn({
+            let written = written.clone();
+            async move {
+                for _ in 0..1000 {
+                    client.write_owned(vec![1]).await.0.unwrap();
+                    written.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        assert!(first_progress(&written).await < 1000);
+        writer.await.unwrap();
+
+        // Always readable once arrived, yields on the budget.
+        sleep(Duration::from_secs(1)).await;
+        let read = Arc::new(AtomicUsize::new(0));
+        let reader = dirtio::spawn({
+            let read = read.clone();
+            async move {
+                let mut buf = [0; 1];
+                for _ in 0..1000 {
+                    server.read_exact(&mut buf).await.unwrap();
+                    read.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        assert!(first_progress(&read).await < 1000);
+        reader.await.unwrap();
+    });
+}
+
+/// Counts the wakeups of a task, through a new waker on each poll.
+struct Counted {
+    waker: Waker,
+    wakes: Arc<AtomicUsize>,
+}
+
+impl Wake for Counted {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+        self.waker.wake_by_ref();
+    }
+}
+
+#[test]
+fn repolled_reads_wait_once() {
+    let mut rt = Runtime::new(3).unwrap();
+    rt.network()
+        .set_latency(Duration::from_millis(10), Duration::from_millis(10));
+    rt.block_on(async {
+        let (mut client, mut server) = connect().await;
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let reader = dirtio::spawn({
+            let wakes = wakes.clone();
+            async move {
+                let mut buf = [0; 1];
+                let mut read = pin!(server.read(&mut buf));
+                let mut polls = 0;
+                poll_fn(|cx| {
+                    let waker = Waker::from(Arc::new(Counted {
+                        waker: cx.waker().clone(),
+                        wakes: wakes.clone(),
+                    }));
+                    let res = read.as_mut().poll(&mut Context::from_waker(&waker));
+                    // Poll again before the data arrives.
+                    polls += 1;
+                    if res.is_pending() && polls < 100 {
+                        cx.waker().wake_by_ref();
+                    }
+                    res
+                })
+                .await
+                .unwrap()
+            }
+        });
+
+        sleep(Duration::from_millis(1)).await;
+        client.write_all(b"x").await.unwrap();
+        assert_eq!(reader.await.unwrap(), 1);
+        // Woken by the write, then by the timer of the arrival.
+        assert!(wakes.load(Ordering::SeqCst) <= 2, "{:?}", wakes);
+    });
+}
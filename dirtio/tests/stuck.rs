@@ -0,0 +1,58 @@
+#![cfg(not(feature = "tracing"))]
+
+use dirtio::runtime::Builder;
+use dirtio::task;
+
+use std::future::poll_fn;
+use std::process::Command;
+use std::sync::mpsc;
+use std::task::Poll;
+
+/// Set by the parent test to run the child test.
+const CHILD: &str = "DIRTIO_STUCK_CHILD";
+
+#[test]
+fn leaked_tasks_are_reported_on_stderr() {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["leaked_task_child", "--exact", "--nocapture"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reports: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.starts_with("dirtio: task"))
+        .collect();
+    assert_eq!(reports.len(), 1, "{}", stderr);
+    assert!(
+        reports[0].contains("(leaky) spawned at")
+            && reports[0].contains("leaked, its wakers were dropped while it was pending"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn leaked_task_child() {
+    if std::env::var_os(CHILD).is_none() {
+        return;
+    }
+
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .detect_leaked_tasks(true)
+        .build()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    let handle = task::Builder::new().name("leaky").spawn_on(
+        poll_fn(move |cx| {
+            tx.send(cx.waker().clone()).unwrap();
+            Poll::<()>::Pending
+        }),
+        rt.handle(),
+    );
+    // The only waker, dropping it leaks the task.
+    drop(rx.recv().unwrap());
+    assert!(rt.block_on(handle).unwrap_err().is_cancelled());
+}
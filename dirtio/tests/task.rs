@@ -0,0 +1,67 @@
+use dirtio::runtime::Builder;
+use dirtio::task;
+use dirtio::time::sleep;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+#[test]
+fn try_id_outside_a_task() {
+    assert_eq!(task::try_id(), None);
+
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .build()
+        .unwrap();
+    // Not a task, the future is polled by the calling thread.
+    rt.block_on(async { assert_eq!(task::try_id(), None) });
+}
+
+#[test]
+#[should_panic(expected = "called outside of a task")]
+fn id_panics_outside_a_task() {
+    task::id();
+}
+
+#[test]
+fn ids_are_unique() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    let handles: Vec<_> = (0..100)
+        .map(|_| rt.handle().spawn(async { (task::id(), task::try_id()) }))
+        .collect();
+
+    let mut ids = HashSet::new();
+    for handle in handles {
+        let expected = handle.id();
+        let (id, try_id) = rt.block_on(handle).unwrap();
+        assert_eq!(id, expected);
+        assert_eq!(try_id, Some(expected));
+        ids.insert(id);
+    }
+    assert_eq!(ids.len(), 100);
+}
+
+#[test]
+fn names_show_up_in_the_dump() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_task_dump()
+        .build()
+        .unwrap();
+    let idle = || async { sleep(Duration::from_secs(3600)).await };
+    let named = task::Builder::new()
+        .name("named")
+        .spawn_on(idle(), rt.handle());
+    let unnamed = rt.handle().spawn(idle());
+
+    let dump = rt.handle().dump();
+    let name = |id| {
+        let task = dump.tasks().iter().find(|task| task.id() == id).unwrap();
+        task.name().map(String::from)
+    };
+    assert_eq!(name(named.id()), Some("named".to_string()));
+    assert_eq!(name(unnamed.id()), None);
+}
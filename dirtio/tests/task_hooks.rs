@@ -0,0 +1,49 @@
+use dirtio::runtime::Builder;
+use dirtio::task::{Id, TaskMeta};
+
+use std::sync::{Arc, Mutex};
+
+type Events = Arc<Mutex<Vec<(&'static str, Id, u32)>>>;
+
+/// Record the hook calls, with the id and the line the task was spawned
+/// at.
+fn record(events: &Events, hook: &'static str) -> impl Fn(&TaskMeta<'_>) {
+    let events = events.clone();
+    move |meta| {
+        let line = meta.spawned_at().line();
+        events.lock().unwrap().push((hook, meta.id(), line));
+    }
+}
+
+#[test]
+fn hooks_run_for_each_spawn_poll_and_termination() {
+    let events = Events::default();
+    let rt = Builder::new_current_thread()
+        .on_task_spawn(record(&events, "spawn"))
+        .on_before_task_poll(record(&events, "before poll"))
+        .on_after_task_poll(record(&events, "after poll"))
+        .on_task_terminate(record(&events, "terminate"))
+        .build()
+        .unwrap();
+
+    let line = line!() + 1;
+    let handle = rt.handle().spawn(async {
+        dirtio::task::yield_now().await;
+        1
+    });
+    let id = handle.id();
+    assert_eq!(rt.block_on(handle).unwrap(), 1);
+
+    let events = events.lock().unwrap();
+    assert_eq!(
+        *events,
+        [
+            ("spawn", id, line),
+            ("before poll", id, line),
+            ("after poll", id, line),
+            ("before poll", id, line),
+            ("after poll", id, line),
+            ("terminate", id, line),
+        ]
+    );
+}
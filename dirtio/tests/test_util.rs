@@ -0,0 +1,174 @@
+#![cfg(feature = "test-util")]
+
+use dirtio::runtime::Builder;
+use dirtio::{assert_pending, assert_ready, assert_ready_eq, assert_ready_err, assert_ready_ok};
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures::Future;
+
+/// Ready once set, holds the waker of the last pending poll until then.
+#[derive(Clone, Default)]
+struct Flag {
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+}
+
+impl Flag {
+    fn set(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = true;
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for Flag {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.0 {
+            return Poll::Ready(());
+        }
+        state.1 = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[test]
+fn spawn_records_wakeups() {
+    let flag = Flag::default();
+    let mut task = dirtio::test::spawn(flag.clone());
+    assert!(!task.is_woken());
+    assert_eq!(task.wake_count(), 0);
+    assert_eq!(task.waker_ref_count(), 0);
+
+    assert_pending!(task.poll());
+    assert!(!task.is_woken());
+    assert_eq!(task.waker_ref_count(), 1);
+    // Polled again, the old waker is replaced.
+    assert_pending!(task.poll());
+    assert_eq!(task.waker_ref_count(), 1);
+
+    flag.set();
+    assert!(task.is_woken());
+    assert_eq!(task.wake_count(), 1);
+    assert_eq!(task.waker_ref_count(), 0);
+
+    assert_ready!(task.poll());
+    assert!(!task.is_woken());
+    assert_eq!(task.wake_count(), 1);
+}
+
+#[test]
+fn spawn_polls_streams() {
+    let mut stream = dirtio::test::spawn(futures::stream::iter([1, 2]));
+    assert_ready_eq!(stream.poll_next(), Some(1));
+    assert_ready_eq!(stream.poll_next(), Some(2));
+    assert_ready_eq!(stream.poll_next(), None);
+}
+
+#[test]
+fn macros_return_the_values() {
+    assert_eq!(assert_ready!(Poll::Ready(1)), 1);
+    assert_eq!(assert_ready!(Poll::Ready(1), "context {}", 1), 1);
+    assert_eq!(assert_ready_ok!(Poll::Ready(Ok::<_, ()>(2))), 2);
+    assert_eq!(assert_ready_err!(Poll::Ready(Err::<(), _>(3))), 3);
+    assert_ready_eq!(Poll::Ready(4), 4);
+    assert_pending!(Poll::<()>::Pending);
+    assert_pending!(Poll::<()>::Pending, "context");
+}
+
+#[test]
+#[should_panic(expected = "pending")]
+fn assert_ready_panics() {
+    assert_ready!(Poll::<()>::Pending);
+}
+
+#[test]
+#[should_panic(expected = "pending; context 1")]
+fn assert_ready_panics_with_message() {
+    assert_ready!(Poll::<()>::Pending, "context {}", 1);
+}
+
+#[test]
+#[should_panic(expected = "error = \"bad\"")]
+fn assert_ready_ok_panics() {
+    assert_ready_ok!(Poll::Ready(Err::<(), _>("bad")));
+}
+
+#[test]
+#[should_panic(expected = "error = \"bad\"; context")]
+fn assert_ready_ok_panics_with_message() {
+    assert_ready_ok!(Poll::Ready(Err::<(), _>("bad")), "context");
+}
+
+#[test]
+#[should_panic(expected = "ok = 5")]
+fn assert_ready_err_panics() {
+    assert_ready_err!(Poll::Ready(Ok::<_, ()>(5)));
+}
+
+#[test]
+#[should_panic(expected = "ok = 5; context")]
+fn assert_ready_err_panics_with_message() {
+    assert_ready_err!(Poll::Ready(Ok::<_, ()>(5)), "context");
+}
+
+#[test]
+#[should_panic(expected = "left: 1\n right: 2")]
+fn assert_ready_eq_panics() {
+    assert_ready_eq!(Poll::Ready(1), 2);
+}
+
+#[test]
+#[should_panic(expected = "failed: context")]
+fn assert_ready_eq_panics_with_message() {
+    assert_ready_eq!(Poll::Ready(1), 2, "context");
+}
+
+#[test]
+#[should_panic(expected = "ready; value = 6")]
+fn assert_pending_panics() {
+    assert_pending!(Poll::Ready(6));
+}
+
+#[test]
+#[should_panic(expected = "ready; value = 6; context")]
+fn assert_pending_panics_with_message() {
+    assert_pending!(Poll::Ready(6), "context");
+}
+
+#[test]
+fn run_until_stalled_runs_yielding_tasks() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let steps = Arc::new(AtomicUsize::new(0));
+    let flag = Flag::default();
+    let handle = rt.handle().spawn({
+        let steps = steps.clone();
+        let flag = flag.clone();
+        async move {
+            for _ in 0..5 {
+                steps.fetch_add(1, Ordering::SeqCst);
+                dirtio::task::yield_now().await;
+            }
+            flag.await;
+            steps.fetch_add(1, Ordering::SeqCst)
+        }
+    });
+    let mut handle = dirtio::test::spawn(handle);
+
+    // Stalls on the flag, after all the yields.
+    rt.run_until_stalled();
+    assert_eq!(steps.load(Ordering::SeqCst), 5);
+    assert_pending!(handle.poll());
+
+    flag.set();
+    rt.run_until_stalled();
+    assert!(handle.is_woken());
+    assert_eq!(assert_ready_ok!(handle.poll()), 5);
+}
@@ -0,0 +1,97 @@
+use dirtio::runtime::Builder;
+use dirtio::time::{sleep, timeout, Instant};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn sleep_timeout_and_instant() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    rt.block_on(async {
+        let start = Instant::now();
+        sleep(Duration::from_millis(20)).await;
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let res = timeout(Duration::from_millis(10), sleep(Duration::from_secs(3600))).await;
+        assert!(res.is_err());
+        assert_eq!(
+            timeout(Duration::from_secs(1), async { 1 }).await.unwrap(),
+            1
+        );
+    });
+}
+
+#[test]
+fn sleep_driven_by_turn() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    assert_eq!(rt.next_timer_deadline(), None);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let _handle = rt.handle().spawn({
+        let done = done.clone();
+        async move {
+            sleep(Duration::from_millis(20)).await;
+            done.store(true, Ordering::SeqCst);
+        }
+    });
+    rt.turn(Duration::ZERO);
+    let deadline = rt.next_timer_deadline().unwrap();
+    assert!(deadline > std::time::Instant::now());
+
+    // A foreign loop waits for the fd or the deadline, nothing else comes.
+    while !done.load(Ordering::SeqCst) {
+        let now = std::time::Instant::now();
+        if let Some(deadline) = rt.next_timer_deadline() {
+            thread::sleep(deadline.saturating_duration_since(now));
+        }
+        rt.turn(Duration::ZERO);
+    }
+    assert!(std::time::Instant::now() >= deadline);
+    assert_eq!(rt.next_timer_deadline(), None);
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn sleep_timeout_and_instant_paused() {
+    let rt = Builder::new_current_thread()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    let wall = std::time::Instant::now();
+    rt.block_on(async {
+        let start = Instant::now();
+        sleep(Duration::from_secs(3600)).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(3600));
+
+        let start = Instant::now();
+        let res = timeout(Duration::from_secs(10), sleep(Duration::from_secs(3600))).await;
+        assert!(res.is_err());
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+    });
+    assert!(wall.elapsed() < Duration::from_secs(1));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn paused_timer_is_due_now() {
+    let rt = Builder::new_current_thread()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    let done = Arc::new(AtomicBool::new(false));
+    let _handle = rt.handle().spawn({
+        let done = done.clone();
+        async move {
+            sleep(Duration::from_secs(3600)).await;
+            done.store(true, Ordering::SeqCst);
+        }
+    });
+    rt.turn(Duration::ZERO);
+    assert!(rt.next_timer_deadline().unwrap() <= std::time::Instant::now());
+
+    while !done.load(Ordering::SeqCst) {
+        rt.turn(Duration::ZERO);
+    }
+}
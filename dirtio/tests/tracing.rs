@@ -0,0 +1,193 @@
+#![cfg(feature = "tracing")]
+
+use dirtio::net::tcp::{TcpListener, TcpStream};
+use dirtio::runtime::Builder;
+use dirtio::task::{self, Id};
+
+use std::fmt;
+use std::future::poll_fn;
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{self, Attributes, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Collects the messages and `task.id` fields of the events.
+#[derive(Clone, Default)]
+struct Events {
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl Events {
+    /// Events seen on all threads, set as the global subscriber on the
+    /// first call.
+    fn global() -> &'static Events {
+        static GLOBAL: OnceLock<Events> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let events = Events::default();
+            tracing::subscriber::set_global_default(events.clone()).unwrap();
+            events
+        })
+    }
+
+    /// The `task.id` fields of the events with the message.
+    fn tasks(&self, message: &str) -> Vec<Option<u64>> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|event| event.message == message)
+            .map(|event| event.task)
+            .collect()
+    }
+
+    /// The messages of the events of the task, the id is not unique
+    /// across runtimes so the message must contain `name` as well.
+    fn messages(&self, task: Id, name: &str) -> Vec<String> {
+        let task = task.to_string().parse::<u64>().unwrap();
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|event| event.task == Some(task) && event.message.contains(name))
+            .map(|event| event.message.clone())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    task: Option<u64>,
+}
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "task.id" {
+            self.task = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "task.id" => self.task = format!("{:?}", value).parse().ok(),
+            _ => {}
+        }
+    }
+}
+
+impl Subscriber for Events {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.events.lock().unwrap().push(fields);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn io_ready_names_the_waiting_task() {
+    let subscriber = Events::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    let rt = Builder::new_current_thread().build().unwrap();
+    let id = rt.block_on(async {
+        let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = dirtio::spawn(async move {
+            listener.accept().await.unwrap();
+        });
+        let id = handle.id();
+        // Let the task register its interest first.
+        dirtio::task::yield_now().await;
+
+        let _stream = TcpStream::connect(addr).unwrap();
+        handle.await.unwrap();
+        id
+    });
+
+    let id = id.to_string().parse::<u64>().unwrap();
+    let tasks = subscriber.tasks("io ready");
+    assert!(tasks.contains(&Some(id)), "{:?}", tasks);
+}
+
+#[test]
+fn slow_polls_are_reported() {
+    let events = Events::global();
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("slow-worker")
+        .warn_on_slow_poll(Duration::from_millis(20))
+        .build()
+        .unwrap();
+    let line = line!() + 1;
+    let handle = task::Builder::new().name("slow").spawn_on(
+        async { thread::sleep(Duration::from_millis(200)) },
+        rt.handle(),
+    );
+    let id = handle.id();
+    rt.block_on(handle).unwrap();
+
+    let task = format!("task {} (slow) spawned at {}:{}:", id, file!(), line);
+    let messages = events.messages(id, &task);
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(
+        messages[0].contains("polled on worker 0 (thread slow-worker)"),
+        "{:?}",
+        messages
+    );
+    assert!(
+        messages[1].contains("blocked worker 0 for"),
+        "{:?}",
+        messages
+    );
+}
+
+#[test]
+fn leaked_tasks_are_reported() {
+    let events = Events::global();
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .detect_leaked_tasks(true)
+        .build()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    let line = line!() + 1;
+    let handle = task::Builder::new().name("leaky").spawn_on(
+        poll_fn(move |cx| {
+            tx.send(cx.waker().clone()).unwrap();
+            Poll::<()>::Pending
+        }),
+        rt.handle(),
+    );
+    let id = handle.id();
+    // The only waker, dropping it leaks the task.
+    drop(rx.recv().unwrap());
+    assert!(rt.block_on(handle).unwrap_err().is_cancelled());
+
+    let task = format!("task {} (leaky) spawned at {}:{}:", id, file!(), line);
+    let messages = events.messages(id, &task);
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert!(
+        messages[0].contains("leaked, its wakers were dropped while it was pending"),
+        "{:?}",
+        messages
+    );
+}
@@ -0,0 +1,130 @@
+#![cfg(target_os = "linux")]
+
+use dirtio::net::tcp::TcpListener;
+use dirtio::runtime::{Builder, Runtime};
+use dirtio::time::sleep;
+
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::AsyncReadExt;
+
+#[repr(C)]
+struct PollFd {
+    fd: RawFd,
+    events: i16,
+    revents: i16,
+}
+
+const POLLIN: i16 = 1;
+
+extern "C" {
+    fn poll(fds: *mut PollFd, nfds: u64, timeout: i32) -> i32;
+}
+
+/// Wait for the fd to become readable, as a foreign event loop does.
+/// Returns `false` on timeout.
+fn wait_readable(fd: RawFd, timeout: Duration) -> bool {
+    let mut fds = PollFd {
+        fd,
+        events: POLLIN,
+        revents: 0,
+    };
+    // Rounded up, or the loop spins until the deadline.
+    let timeout = timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32;
+    // Safety: the fd struct outlives the call.
+    let res = unsafe { poll(&mut fds, 1, timeout) };
+    assert!(res >= 0, "poll failed");
+    res == 1 && fds.revents & POLLIN != 0
+}
+
+/// Drive the runtime from a foreign loop until `done`, waits for its fd
+/// or its next timer in between. Returns the number of wake-ups.
+fn drive(rt: &Runtime, done: impl Fn() -> bool) -> usize {
+    let start = Instant::now();
+    let mut wakeups = 0;
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(5), "stalled");
+        let timeout = rt
+            .next_timer_deadline()
+            .map_or(Duration::from_secs(5), |deadline| {
+                deadline.saturating_duration_since(Instant::now())
+            });
+        wait_readable(rt.as_raw_fd(), timeout);
+        rt.turn(Duration::ZERO);
+        wakeups += 1;
+    }
+    wakeups
+}
+
+#[test]
+fn fd_becomes_readable_for_io() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let received = Arc::new(AtomicUsize::new(0));
+    let addr = {
+        let _guard = rt.enter();
+        let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = received.clone();
+        let _handle = rt.handle().spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            received.store(buf.len(), Ordering::SeqCst);
+        });
+        addr
+    };
+    rt.turn(Duration::ZERO);
+    assert!(!wait_readable(rt.as_raw_fd(), Duration::ZERO));
+
+    let client = thread::spawn(move || {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        stream.write_all(b"hello").unwrap();
+    });
+    drive(&rt, || received.load(Ordering::SeqCst) == 5);
+    client.join().unwrap();
+}
+
+#[test]
+fn fd_becomes_readable_for_tasks() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let handle = rt.handle().clone();
+    let ran = Arc::new(AtomicUsize::new(0));
+    thread::spawn({
+        let ran = ran.clone();
+        move || {
+            thread::sleep(Duration::from_millis(20));
+            let _handle = handle.spawn(async move {
+                ran.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    assert!(wait_readable(rt.as_raw_fd(), Duration::from_secs(5)));
+    rt.turn(Duration::ZERO);
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn timers_fire_by_the_next_deadline() {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let fired = Arc::new(AtomicUsize::new(0));
+    for i in 1..=3 {
+        let fired = fired.clone();
+        let _handle = rt.handle().spawn(async move {
+            sleep(Duration::from_millis(10 * i)).await;
+            fired.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    let start = Instant::now();
+    let wakeups = drive(&rt, || fired.load(Ordering::SeqCst) == 3);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    // Woken up for the timers, not busy polling.
+    assert!(wakeups < 20, "{}", wakeups);
+}
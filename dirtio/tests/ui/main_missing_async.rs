@@ -0,0 +1,2 @@
+#[dirtio::main]
+fn main() {}
@@ -0,0 +1,2 @@
+#[dirtio::main(start_paused = true)]
+async fn main() {}
@@ -0,0 +1,2 @@
+#[dirtio::main(worker_threads = 0)]
+async fn main() {}
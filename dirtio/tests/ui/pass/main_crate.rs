@@ -0,0 +1,9 @@
+mod reexport {
+    pub use dirtio as rt;
+}
+
+#[dirtio::main(crate = "reexport::rt", flavor = "current_thread")]
+async fn main() {
+    let handle = reexport::rt::spawn(async { 1 });
+    assert_eq!(handle.await.unwrap(), 1);
+}
@@ -0,0 +1,8 @@
+use std::num::ParseIntError;
+
+#[dirtio::main(worker_threads = 2)]
+async fn main() -> Result<(), ParseIntError> {
+    let n: u32 = "5".parse()?;
+    assert_eq!(n, 5);
+    Ok(())
+}
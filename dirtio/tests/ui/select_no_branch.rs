@@ -0,0 +1,5 @@
+async fn run() {
+    dirtio::select! {};
+}
+
+fn main() {}
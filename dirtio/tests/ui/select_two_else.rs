@@ -0,0 +1,9 @@
+async fn run() {
+    dirtio::select! {
+        v = async { 1 } => v,
+        else => 0,
+        else => 1,
+    };
+}
+
+fn main() {}
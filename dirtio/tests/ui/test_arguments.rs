@@ -0,0 +1,4 @@
+#[dirtio::test]
+async fn arguments(_n: usize) {}
+
+fn main() {}
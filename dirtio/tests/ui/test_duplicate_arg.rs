@@ -0,0 +1,4 @@
+#[dirtio::test(flavor = "current_thread", flavor = "multi_thread")]
+async fn duplicate_arg() {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[dirtio::test]
+fn missing_async() {}
+
+fn main() {}
@@ -0,0 +1,5 @@
+#[dirtio::test]
+#[test]
+async fn stray_test() {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[dirtio::test(threads = 2)]
+async fn unknown_arg() {}
+
+fn main() {}
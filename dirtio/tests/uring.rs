@@ -0,0 +1,127 @@
+#![cfg(all(feature = "io-uring", target_os = "linux"))]
+
+use dirtio::fs::File;
+use dirtio::net::tcp::{TcpListener, TcpStream};
+use dirtio::net::udp::UdpSocket;
+use dirtio::time::timeout;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A file in the temp dir, removed on drop.
+struct TempPath(PathBuf);
+
+impl TempPath {
+    fn new(name: &str) -> Self {
+        let name = format!("dirtio-{}-{}", name, std::process::id());
+        Self(std::env::temp_dir().join(name))
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[dirtio::test]
+async fn file_round_trip() {
+    let path = TempPath::new("round-trip");
+    let file = File::create(&path.0).unwrap();
+    let (res, buf) = file.write_at(b"hello world".to_vec(), 0).await;
+    assert_eq!(res.unwrap(), 11);
+    assert_eq!(buf, b"hello world");
+    let (res, _) = file.write_at(b"there".to_vec(), 6).await;
+    assert_eq!(res.unwrap(), 5);
+    file.sync_all().await.unwrap();
+
+    let file = File::open(&path.0).unwrap();
+    let (res, buf) = file.read_at(vec![0; 32], 0).await;
+    let n = res.unwrap();
+    assert_eq!(&buf[..n], b"hello there");
+    let (res, _) = file.read_at(vec![0; 32], 11).await;
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[dirtio::test]
+async fn file_errors_are_returned() {
+    let path = TempPath::new("errors");
+    std::fs::write(&path.0, b"data").unwrap();
+    // Opened read-only.
+    let file = File::open(&path.0).unwrap();
+    let (res, buf) = file.write_at(b"more".to_vec(), 0).await;
+    assert!(res.is_err());
+    assert_eq!(buf, b"more");
+}
+
+#[dirtio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn many_operations_in_flight() {
+    let path = TempPath::new("in-flight");
+    std::fs::write(&path.0, (0..=255).collect::<Vec<u8>>()).unwrap();
+    let file = Arc::new(File::open(&path.0).unwrap());
+
+    // More than the submission queue holds.
+    let handles: Vec<_> = (0..1000u64)
+        .map(|i| {
+            let file = file.clone();
+            dirtio::spawn(async move {
+                let (res, buf) = file.read_at(vec![0; 1], i % 256).await;
+                assert_eq!(res.unwrap(), 1);
+                assert_eq!(buf[0] as u64, i % 256);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+async fn connect() -> (TcpStream, TcpStream) {
+    let mut listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (client, server)
+}
+
+#[dirtio::test]
+async fn tcp_owned_buffers() {
+    let (client, server) = connect().await;
+    let (res, buf) = client.write_owned(b"ping".to_vec()).await;
+    assert_eq!(res.unwrap(), 4);
+    assert_eq!(buf, b"ping");
+
+    let (res, buf) = server.read_owned(vec![0; 16]).await;
+    let n = res.unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(buf.len(), 16);
+
+    drop(client);
+    let (res, _) = server.read_owned(vec![0; 16]).await;
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[dirtio::test]
+async fn udp_owned_buffers() {
+    let server = UdpSocket::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    client.connect(server.local_addr().unwrap()).unwrap();
+    server.connect(client.local_addr().unwrap()).unwrap();
+
+    let (res, _) = client.send_owned(b"query".to_vec()).await;
+    assert_eq!(res.unwrap(), 5);
+    let (res, buf) = server.recv_owned(vec![0; 16]).await;
+    assert_eq!(&buf[..res.unwrap()], b"query");
+}
+
+#[dirtio::test]
+async fn dropped_operation_keeps_the_runtime_working() {
+    let (client, server) = connect().await;
+    // Nothing to read, the operation is dropped in flight.
+    let res = timeout(Duration::from_millis(10), server.read_owned(vec![0; 16])).await;
+    assert!(res.is_err());
+
+    client.write_owned(b"late".to_vec()).await.0.unwrap();
+    let (res, buf) = server.read_owned(vec![0; 16]).await;
+    assert_eq!(&buf[..res.unwrap()], b"late");
+}
@@ -0,0 +1,53 @@
+#![cfg(not(feature = "tracing"))]
+
+use dirtio::runtime::Builder;
+use dirtio::task;
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Set by the parent test to run the child test.
+const CHILD: &str = "DIRTIO_WATCHDOG_CHILD";
+
+#[test]
+fn slow_polls_are_reported_on_stderr() {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["slow_poll_child", "--exact", "--nocapture"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reports: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.starts_with("dirtio: task"))
+        .collect();
+    assert_eq!(reports.len(), 2, "{}", stderr);
+    assert!(
+        reports[0].contains("(slow) spawned at")
+            && reports[0].contains("polled on worker 0 (thread slow-worker)"),
+        "{}",
+        stderr
+    );
+    assert!(reports[1].contains("blocked worker 0 for"), "{}", stderr);
+}
+
+#[test]
+fn slow_poll_child() {
+    if std::env::var_os(CHILD).is_none() {
+        return;
+    }
+
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("slow-worker")
+        .warn_on_slow_poll(Duration::from_millis(20))
+        .build()
+        .unwrap();
+    let handle = task::Builder::new().name("slow").spawn_on(
+        async { thread::sleep(Duration::from_millis(200)) },
+        rt.handle(),
+    );
+    rt.block_on(handle).unwrap();
+}